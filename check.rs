@@ -0,0 +1,103 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use similar::TextDiff;
+
+/// A unified diff from a file in the output folder to the extracted catalog, or `None` if they're
+/// the same. A missing file is compared as an empty one.
+pub fn diff(path: &Path, existing: Option<&str>, extracted: &str) -> Option<String> {
+    if existing == Some(extracted) {
+        return None;
+    }
+    let path = path.to_str().unwrap_or("unknown");
+    Some(
+        TextDiff::from_lines(existing.unwrap_or_default(), extracted)
+            .unified_diff()
+            .header(existing.map_or("/dev/null", |_| path), path)
+            .to_string(),
+    )
+}
+
+/// The POT files in the output folder for domains that don't have any messages any more
+pub fn stale_files(output_folder: &Path, is_domain: impl Fn(&str) -> bool) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(output_folder) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path.extension().is_some_and(|ext| ext == "pot")
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|domain| !is_domain(domain))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn diffs_catalogs() {
+        let path = Path::new("locales/default.pot");
+        assert_eq!(
+            diff(path, Some("msgid \"Save\"\n"), "msgid \"Save\"\n"),
+            None
+        );
+        assert_eq!(
+            diff(
+                path,
+                Some("msgid \"Cancel\"\nmsgstr \"\"\n\nmsgid \"Save\"\nmsgstr \"\"\n"),
+                "msgid \"Save\"\nmsgstr \"\"\n\nmsgid \"Undo\"\nmsgstr \"\"\n"
+            )
+            .unwrap(),
+            r#"--- locales/default.pot
++++ locales/default.pot
+@@ -1,5 +1,5 @@
+-msgid "Cancel"
++msgid "Save"
+ msgstr ""
+ 
+-msgid "Save"
++msgid "Undo"
+ msgstr ""
+"#
+        );
+        assert_eq!(
+            diff(path, None, "msgid \"Save\"\n").unwrap(),
+            r#"--- /dev/null
++++ locales/default.pot
+@@ -0,0 +1 @@
++msgid "Save"
+"#
+        );
+    }
+
+    #[test]
+    fn finds_stale_files() {
+        let output_folder = std::env::temp_dir().join("ts-gettext-extractor-stale-files");
+        let _ = fs::remove_dir_all(&output_folder);
+        fs::create_dir_all(&output_folder).unwrap();
+        for file in ["default.pot", "billing.pot", "notes.txt"] {
+            fs::write(output_folder.join(file), "").unwrap();
+        }
+        assert_eq!(
+            stale_files(&output_folder, |domain| domain == "default"),
+            vec![output_folder.join("billing.pot")]
+        );
+        assert_eq!(
+            stale_files(&output_folder.join("missing"), |_| false),
+            Vec::<PathBuf>::new()
+        );
+    }
+}
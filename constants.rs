@@ -0,0 +1,521 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+};
+
+use swc_common::{Span, Spanned, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_visit_type, Visit, VisitWith};
+
+use crate::{
+    eval::{eval_string_with, unwrap_expr, EvalError, EvalErrorKind, Resolve},
+    exports::{ExportIndex, ExportedValue, ImportTarget},
+};
+
+/// How deep references can be followed, e.g. `const A = B; const B = C;`, so that cycles end
+const MAX_DEPTH: usize = 32;
+
+/// What a name is bound to in a scope
+#[derive(Debug)]
+enum Binding {
+    /// A `const` initialised with an expression that might evaluate to a string
+    Const(Box<Expr>),
+    /// A `let` or `var`, which can be reassigned
+    Mutable,
+    /// Anything else that shadows outer bindings, like parameters, functions or imports
+    Opaque,
+    /// An import from a project file that couldn't be found, with the imported path
+    Unresolved(String),
+}
+
+#[derive(Debug)]
+struct Scope {
+    parent: Option<usize>,
+    bindings: HashMap<String, Binding>,
+}
+
+/// The `const` bindings of a file, so that `__p(CTX, 'File')` or `__(MESSAGES.save)` can be
+/// extracted when they refer to string literals.
+///
+/// The visitor enters and leaves scopes as it walks the file. Scopes are kept after being left,
+/// as constants are evaluated in the scope they were declared in.
+#[derive(Debug)]
+pub struct Constants {
+    scopes: Vec<Scope>,
+    current: usize,
+    /// Names of objects whose properties are assigned somewhere in the file, like `MESSAGES.save = '...'`
+    mutated: HashSet<String>,
+}
+impl Default for Constants {
+    fn default() -> Self {
+        Self {
+            scopes: vec![Scope {
+                parent: None,
+                bindings: HashMap::new(),
+            }],
+            current: 0,
+            mutated: HashSet::new(),
+        }
+    }
+}
+impl Constants {
+    /// Declares the top-level bindings of a module. With an index of exported constants and the
+    /// path of the module, imported constants are declared too.
+    pub fn declare_module(&mut self, module: &Module, exports: Option<(&ExportIndex, &Path)>) {
+        let mut collector = MutationCollector::default();
+        module.visit_with(&mut collector);
+        self.mutated.extend(collector.names);
+
+        self.declare_vars(module);
+        self.declare_module_items(&module.body, exports);
+    }
+
+    pub fn declare_module_items(
+        &mut self,
+        items: &[ModuleItem],
+        exports: Option<(&ExportIndex, &Path)>,
+    ) {
+        for item in items {
+            match item {
+                ModuleItem::Stmt(stmt) => self.declare_stmts([stmt]),
+                ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+                    self.declare_decl(decl)
+                }
+                ModuleItem::ModuleDecl(ModuleDecl::Import(import)) => {
+                    self.declare_import(import, exports)
+                }
+                ModuleItem::ModuleDecl(_) => {}
+            }
+        }
+    }
+
+    fn declare_import(&mut self, import: &ImportDecl, exports: Option<(&ExportIndex, &Path)>) {
+        let target = match exports {
+            Some((index, path)) if !import.type_only => index.resolve(path, &import.src.value),
+            _ => ImportTarget::External,
+        };
+        for specifier in &import.specifiers {
+            let binding = match (specifier, &target) {
+                (_, ImportTarget::Unresolved) => Binding::Unresolved(import.src.value.to_string()),
+                (
+                    ImportSpecifier::Named(ImportNamedSpecifier {
+                        local, imported, ..
+                    }),
+                    ImportTarget::Module(exports),
+                ) => {
+                    let imported = imported
+                        .as_ref()
+                        .map(crate::imports::module_export_name)
+                        .unwrap_or_else(|| local.sym.to_string());
+                    match exports.get(&imported) {
+                        Some(value) => Binding::Const(Box::new(value.to_expr())),
+                        None => Binding::Opaque,
+                    }
+                }
+                (ImportSpecifier::Namespace(_), ImportTarget::Module(exports)) => {
+                    let values = exports
+                        .iter()
+                        .map(|(name, value)| (name.clone(), value.clone()))
+                        .collect();
+                    Binding::Const(Box::new(ExportedValue::Object(values).to_expr()))
+                }
+                _ => Binding::Opaque,
+            };
+            let (ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+            | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+            | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. })) = specifier;
+            self.declare(&local.sym, binding);
+        }
+    }
+
+    /// The value of a top-level constant, for the index of exported constants. Properties of
+    /// objects that aren't strings are left out.
+    pub fn exported_value(&self, name: &str) -> Option<ExportedValue> {
+        let (expr, scope) = self.lookup_name(0, name, DUMMY_SP).ok()?;
+        self.value_of(expr, scope, 0)
+    }
+
+    fn value_of(&self, expr: &Expr, scope: usize, depth: usize) -> Option<ExportedValue> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        match unwrap_expr(expr) {
+            Expr::Object(ObjectLit { props, .. }) => {
+                let mut values = BTreeMap::new();
+                for prop in props {
+                    let PropOrSpread::Prop(prop) = prop else {
+                        // A spread could override the properties before it
+                        values.clear();
+                        continue;
+                    };
+                    let (name, value) = match prop.as_ref() {
+                        Prop::KeyValue(KeyValueProp { key, value }) => {
+                            (prop_name(key), self.value_of(value, scope, depth + 1))
+                        }
+                        Prop::Shorthand(Ident { sym, span, .. }) => (
+                            Some(sym.as_ref()),
+                            self.lookup_name(scope, sym, *span)
+                                .ok()
+                                .and_then(|(value, scope)| self.value_of(value, scope, depth + 1)),
+                        ),
+                        prop => (prop_key(prop).and_then(prop_name), None),
+                    };
+                    match (name, value) {
+                        (Some(name), Some(value)) => {
+                            values.insert(name.to_string(), value);
+                        }
+                        (Some(name), None) => {
+                            values.remove(name);
+                        }
+                        // Computed keys could override any property
+                        (None, _) => values.clear(),
+                    }
+                }
+                Some(ExportedValue::Object(values))
+            }
+            Expr::Ident(_) | Expr::Member(_) => {
+                let (value, scope) = self.lookup(scope, expr, depth + 1).ok()?;
+                self.value_of(value, scope, depth + 1)
+            }
+            _ => eval_string_with(
+                expr,
+                &ScopedResolver {
+                    constants: self,
+                    scope,
+                    depth: depth + 1,
+                },
+            )
+            .ok()
+            .map(ExportedValue::String),
+        }
+    }
+
+    /// Declares the block-scoped bindings of statements: `let`, `const`, functions and classes
+    pub fn declare_stmts<'s>(&mut self, stmts: impl IntoIterator<Item = &'s Stmt>) {
+        for stmt in stmts {
+            if let Stmt::Decl(decl) = stmt {
+                self.declare_decl(decl);
+            }
+        }
+    }
+
+    /// Declares the `var` bindings of a function body, which are hoisted out of nested blocks
+    pub fn declare_vars<N: VisitWith<VarCollector>>(&mut self, body: &N) {
+        let mut collector = VarCollector::default();
+        body.visit_with(&mut collector);
+        for name in collector.names {
+            self.declare(&name, Binding::Mutable);
+        }
+    }
+
+    /// Declares names that can't be evaluated, like function parameters
+    pub fn declare_pat(&mut self, pat: &Pat) {
+        let mut names = Vec::new();
+        pat_names(pat, &mut names);
+        for name in names {
+            self.declare(&name, Binding::Opaque);
+        }
+    }
+
+    pub fn declare_var_decl(&mut self, decl: &VarDecl) {
+        for declarator in &decl.decls {
+            match (decl.kind, &declarator.name, &declarator.init) {
+                (VarDeclKind::Const, Pat::Ident(BindingIdent { id, .. }), Some(init))
+                    if may_be_string(init) =>
+                {
+                    self.declare(&id.sym, Binding::Const(init.clone()))
+                }
+                (VarDeclKind::Let, name, _) => {
+                    let mut names = Vec::new();
+                    pat_names(name, &mut names);
+                    for name in names {
+                        self.declare(&name, Binding::Mutable);
+                    }
+                }
+                // `var` is declared by the enclosing function
+                (VarDeclKind::Var, ..) => {}
+                (_, name, _) => self.declare_pat(name),
+            }
+        }
+    }
+
+    fn declare_decl(&mut self, decl: &Decl) {
+        match decl {
+            Decl::Var(var) => self.declare_var_decl(var),
+            Decl::Class(ClassDecl { ident, .. }) | Decl::Fn(FnDecl { ident, .. }) => {
+                self.declare(&ident.sym, Binding::Opaque)
+            }
+            Decl::TsEnum(decl) => self.declare(&decl.id.sym, Binding::Opaque),
+            _ => {}
+        }
+    }
+
+    fn declare(&mut self, name: &str, binding: Binding) {
+        self.scopes[self.current]
+            .bindings
+            .insert(name.to_string(), binding);
+    }
+
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(Scope {
+            parent: Some(self.current),
+            bindings: HashMap::new(),
+        });
+        self.current = self.scopes.len() - 1;
+    }
+
+    pub fn leave_scope(&mut self) {
+        self.current = self.scopes[self.current]
+            .parent
+            .expect("Left the module scope");
+    }
+
+    /// Evaluates a reference in the given scope
+    fn resolve_in(&self, scope: usize, expr: &Expr, depth: usize) -> Result<String, EvalError> {
+        let (value, scope) = self.lookup(scope, expr, depth)?;
+        eval_string_with(
+            value,
+            &ScopedResolver {
+                constants: self,
+                scope,
+                depth: depth + 1,
+            },
+        )
+    }
+
+    /// Finds the expression a reference is bound to, along with the scope it was declared in
+    fn lookup(&self, scope: usize, expr: &Expr, depth: usize) -> Result<(&Expr, usize), EvalError> {
+        let non_literal = EvalError {
+            span: expr.span(),
+            kind: EvalErrorKind::NonLiteral,
+        };
+        if depth > MAX_DEPTH {
+            return Err(non_literal);
+        }
+
+        match unwrap_expr(expr) {
+            Expr::Ident(Ident { sym, span, .. }) => self.lookup_name(scope, sym, *span),
+            Expr::Member(MemberExpr { obj, prop, .. }) => {
+                let name = match prop {
+                    MemberProp::Ident(Ident { sym, .. }) => sym.as_ref(),
+                    MemberProp::Computed(ComputedPropName { expr, .. }) => match expr.as_ref() {
+                        Expr::Lit(Lit::Str(Str { value, .. })) => value.as_ref(),
+                        _ => return Err(non_literal),
+                    },
+                    MemberProp::PrivateName(_) => return Err(non_literal),
+                };
+
+                // Follow aliases like `const LABELS = MESSAGES` to the object literal
+                let mut depth = depth + 1;
+                let (mut object, mut scope) = self.lookup(scope, obj, depth)?;
+                while matches!(unwrap_expr(object), Expr::Ident(_) | Expr::Member(_)) {
+                    depth += 1;
+                    (object, scope) = self.lookup(scope, object, depth)?;
+                }
+                let Expr::Object(ObjectLit { props, .. }) = unwrap_expr(object) else {
+                    return Err(non_literal);
+                };
+
+                // The last property with that name wins, unless a spread could override it
+                for prop in props.iter().rev() {
+                    let PropOrSpread::Prop(prop) = prop else {
+                        return Err(non_literal);
+                    };
+                    match prop.as_ref() {
+                        Prop::KeyValue(KeyValueProp { key, value })
+                            if prop_name(key) == Some(name) =>
+                        {
+                            return Ok((value.as_ref(), scope))
+                        }
+                        Prop::Shorthand(Ident { sym, span, .. }) if sym == name => {
+                            return self.lookup_name(scope, sym, *span)
+                        }
+                        Prop::Shorthand(_) => {}
+                        // Computed keys, getters and methods could also be the property
+                        prop => {
+                            if prop_key(prop)
+                                .and_then(prop_name)
+                                .is_none_or(|key| key == name)
+                            {
+                                return Err(non_literal);
+                            }
+                        }
+                    }
+                }
+                Err(non_literal)
+            }
+            _ => Err(non_literal),
+        }
+    }
+
+    fn lookup_name(
+        &self,
+        scope: usize,
+        name: &str,
+        span: Span,
+    ) -> Result<(&Expr, usize), EvalError> {
+        let mut scope = Some(scope);
+        while let Some(index) = scope {
+            match self.scopes[index].bindings.get(name) {
+                Some(Binding::Const(init))
+                    if self.mutated.contains(name)
+                        && matches!(unwrap_expr(init), Expr::Object(_)) =>
+                {
+                    break
+                }
+                Some(Binding::Const(init)) => return Ok((init.as_ref(), index)),
+                Some(Binding::Mutable) => break,
+                Some(Binding::Unresolved(source)) => {
+                    return Err(EvalError {
+                        span,
+                        kind: EvalErrorKind::UnresolvedImport(source.clone()),
+                    })
+                }
+                Some(Binding::Opaque) => {
+                    return Err(EvalError {
+                        span,
+                        kind: EvalErrorKind::NonLiteral,
+                    })
+                }
+                None => scope = self.scopes[index].parent,
+            }
+        }
+        Err(EvalError {
+            span,
+            kind: match scope {
+                Some(_) => EvalErrorKind::MutableBinding,
+                // Globals or undeclared names
+                None => EvalErrorKind::NonLiteral,
+            },
+        })
+    }
+}
+impl Resolve for Constants {
+    fn resolve(&self, expr: &Expr) -> Result<String, EvalError> {
+        // Errors in the declaration of a constant are reported where it is used
+        self.resolve_in(self.current, expr, 0)
+            .map_err(|error| EvalError {
+                span: expr.span(),
+                ..error
+            })
+    }
+}
+
+/// Resolves references in the initialiser of a constant, from the scope it was declared in
+struct ScopedResolver<'a> {
+    constants: &'a Constants,
+    scope: usize,
+    depth: usize,
+}
+impl Resolve for ScopedResolver<'_> {
+    fn resolve(&self, expr: &Expr) -> Result<String, EvalError> {
+        self.constants.resolve_in(self.scope, expr, self.depth)
+    }
+}
+
+/// Whether a `const` initialiser is worth keeping, to avoid copying functions or JSX
+fn may_be_string(expr: &Expr) -> bool {
+    matches!(
+        unwrap_expr(expr),
+        Expr::Lit(Lit::Str(_))
+            | Expr::Tpl(_)
+            | Expr::Bin(BinExpr {
+                op: BinaryOp::Add,
+                ..
+            })
+            | Expr::Ident(_)
+            | Expr::Member(_)
+            | Expr::Object(_)
+    )
+}
+
+fn prop_key(prop: &Prop) -> Option<&PropName> {
+    match prop {
+        Prop::KeyValue(KeyValueProp { key, .. })
+        | Prop::Getter(GetterProp { key, .. })
+        | Prop::Setter(SetterProp { key, .. })
+        | Prop::Method(MethodProp { key, .. }) => Some(key),
+        Prop::Shorthand(_) | Prop::Assign(_) => None,
+    }
+}
+
+/// The static name of a property key. Computed keys are `None`, as they could be anything.
+fn prop_name(key: &PropName) -> Option<&str> {
+    match key {
+        PropName::Ident(Ident { sym, .. }) => Some(sym),
+        PropName::Str(Str { value, .. }) => Some(value),
+        PropName::Num(_) | PropName::Computed(_) | PropName::BigInt(_) => None,
+    }
+}
+
+/// Collects the names bound by a pattern, like `a` and `b` in `{ a, b: [b] }`
+fn pat_names(pat: &Pat, names: &mut Vec<String>) {
+    match pat {
+        Pat::Ident(BindingIdent { id, .. }) => names.push(id.sym.to_string()),
+        Pat::Array(ArrayPat { elems, .. }) => {
+            for elem in elems.iter().flatten() {
+                pat_names(elem, names);
+            }
+        }
+        Pat::Object(ObjectPat { props, .. }) => {
+            for prop in props {
+                match prop {
+                    ObjectPatProp::KeyValue(KeyValuePatProp { value, .. }) => {
+                        pat_names(value, names)
+                    }
+                    ObjectPatProp::Assign(AssignPatProp { key, .. }) => {
+                        names.push(key.sym.to_string())
+                    }
+                    ObjectPatProp::Rest(RestPat { arg, .. }) => pat_names(arg, names),
+                }
+            }
+        }
+        Pat::Rest(RestPat { arg, .. }) => pat_names(arg, names),
+        Pat::Assign(AssignPat { left, .. }) => pat_names(left, names),
+        Pat::Invalid(_) | Pat::Expr(_) => {}
+    }
+}
+
+/// Collects the `var` declarations of a function body, without going into nested functions
+#[derive(Default)]
+pub struct VarCollector {
+    names: Vec<String>,
+}
+impl Visit for VarCollector {
+    noop_visit_type!();
+
+    fn visit_var_decl(&mut self, n: &VarDecl) {
+        if n.kind == VarDeclKind::Var {
+            for declarator in &n.decls {
+                pat_names(&declarator.name, &mut self.names);
+            }
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_function(&mut self, _: &Function) {}
+
+    fn visit_arrow_expr(&mut self, _: &ArrowExpr) {}
+}
+
+/// Collects the objects whose properties are assigned, like `MESSAGES` in `MESSAGES.save = '...'`
+#[derive(Default)]
+struct MutationCollector {
+    names: HashSet<String>,
+}
+impl Visit for MutationCollector {
+    noop_visit_type!();
+
+    fn visit_simple_assign_target(&mut self, n: &SimpleAssignTarget) {
+        if let SimpleAssignTarget::Member(MemberExpr { obj, .. }) = n {
+            let mut object = obj.as_ref();
+            while let Expr::Member(MemberExpr { obj, .. }) = unwrap_expr(object) {
+                object = obj;
+            }
+            if let Expr::Ident(Ident { sym, .. }) = unwrap_expr(object) {
+                self.names.insert(sym.to_string());
+            }
+        }
+        n.visit_children_with(self);
+    }
+}
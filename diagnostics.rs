@@ -0,0 +1,184 @@
+use std::fmt;
+
+use swc_common::{errors::Handler, SourceMap, Span};
+
+use crate::eval::{EvalError, EvalErrorKind};
+
+/// Why a gettext call was skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// An argument isn't a string literal, e.g. `__(someVariable)`
+    NonLiteralArgument,
+    /// A template literal argument has `${}` expressions, e.g. ``__(`Hello ${name}`)``
+    InterpolatedTemplate,
+    /// An argument refers to a `let` or `var` binding, or to an object whose properties are
+    /// assigned, e.g. `let CTX = 'menu'; __p(CTX, 'File')`
+    MutableBinding,
+    /// An argument refers to an import from a project file that couldn't be found, with the imported path
+    UnresolvedImport { source: String },
+    /// The call has fewer arguments than the keyword refers to, e.g. `__n('one')`
+    TooFewArguments { expected: usize, found: usize },
+    /// A curried tagged template has more arguments than the keyword refers to, so the template
+    /// wouldn't be used, e.g. `` __('x')`y` ``
+    TooManyArguments { expected: usize, found: usize },
+    /// A translation component has no msgid attribute and no children
+    EmptyMessage,
+    /// An option of an `i18n:` comment on the call isn't known or has an invalid value,
+    /// e.g. `/* i18n: max-length=short */`. The call is still extracted.
+    InvalidDirective { directive: String },
+}
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::NonLiteralArgument => write!(f, "argument is not a string literal"),
+            DiagnosticKind::InterpolatedTemplate => {
+                write!(f, "template literal has interpolated expressions")
+            }
+            DiagnosticKind::MutableBinding => {
+                write!(f, "argument refers to a binding that isn't constant")
+            }
+            DiagnosticKind::UnresolvedImport { source } => write!(
+                f,
+                "argument is imported from `{}`, which couldn't be resolved",
+                source
+            ),
+            DiagnosticKind::TooFewArguments { expected, found } => {
+                write!(f, "expected {} arguments, found {}", expected, found)
+            }
+            DiagnosticKind::TooManyArguments { expected, found } => write!(
+                f,
+                "expected {} arguments including the template, found {}",
+                expected, found
+            ),
+            DiagnosticKind::EmptyMessage => write!(f, "message is empty"),
+            DiagnosticKind::InvalidDirective { directive } => {
+                write!(f, "`{}` isn't a valid i18n directive", directive)
+            }
+        }
+    }
+}
+impl From<EvalErrorKind> for DiagnosticKind {
+    fn from(kind: EvalErrorKind) -> Self {
+        match kind {
+            EvalErrorKind::NonLiteral => DiagnosticKind::NonLiteralArgument,
+            EvalErrorKind::InterpolatedTemplate => DiagnosticKind::InterpolatedTemplate,
+            EvalErrorKind::MutableBinding => DiagnosticKind::MutableBinding,
+            EvalErrorKind::UnresolvedImport(source) => DiagnosticKind::UnresolvedImport { source },
+        }
+    }
+}
+
+/// A gettext call that couldn't be extracted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub file: String,
+    pub line: usize,
+    /// One-based column, as shown by editors
+    pub column: usize,
+    /// The name of the function that was called
+    pub keyword: String,
+    pub kind: DiagnosticKind,
+}
+impl Diagnostic {
+    pub fn new(cm: &SourceMap, span: Span, keyword: &str, kind: DiagnosticKind) -> Self {
+        let loc = cm.lookup_char_pos(span.lo);
+        Self {
+            span,
+            file: loc.file.name.to_string(),
+            line: loc.line,
+            column: loc.col_display + 1,
+            keyword: keyword.to_string(),
+            kind,
+        }
+    }
+
+    pub fn from_eval_error(cm: &SourceMap, error: EvalError, keyword: &str) -> Self {
+        Self::new(cm, error.span, keyword, error.kind.into())
+    }
+
+    /// Whether the call was skipped, rather than extracted without some of its comments
+    pub fn skips_call(&self) -> bool {
+        !matches!(self.kind, DiagnosticKind::InvalidDirective { .. })
+    }
+
+    pub fn message(&self) -> String {
+        if self.skips_call() {
+            format!("Skipping `{}` call: {}", self.keyword, self.kind)
+        } else {
+            format!("Ignoring comment on `{}` call: {}", self.keyword, self.kind)
+        }
+    }
+
+    /// Prints the diagnostic with a code snippet
+    pub fn emit(&self, handler: &Handler, as_error: bool) {
+        if as_error {
+            handler.struct_span_err(self.span, &self.message()).emit();
+        } else {
+            handler.struct_span_warn(self.span, &self.message()).emit();
+        }
+    }
+}
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: {}",
+            self.file,
+            self.line,
+            self.column,
+            self.message()
+        )
+    }
+}
+
+/// All diagnostics reported while extracting a project
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+    /// Calls skipped because of `gettext-extractor-disable` comments
+    suppressed: usize,
+    /// Files skipped because they couldn't be read or parsed
+    unparseable_files: usize,
+}
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn extend(&mut self, diagnostics: impl IntoIterator<Item = Diagnostic>) {
+        self.entries.extend(diagnostics);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// How many calls couldn't be extracted
+    pub fn skipped_calls(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|diagnostic| diagnostic.skips_call())
+            .count()
+    }
+
+    pub fn add_suppressed(&mut self, count: usize) {
+        self.suppressed += count;
+    }
+
+    pub fn suppressed(&self) -> usize {
+        self.suppressed
+    }
+
+    pub fn add_unparseable_file(&mut self) {
+        self.unparseable_files += 1;
+    }
+
+    pub fn unparseable_files(&self) -> usize {
+        self.unparseable_files
+    }
+}
@@ -0,0 +1,166 @@
+use swc_common::comments::Comment;
+
+const PREFIX: &str = "i18n:";
+
+/// An option of an `i18n:` comment next to a call, like
+/// `/* i18n: context=toolbar, max-length=20, no-wrap */`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+    /// `context=toolbar` sets the msgctxt of the message
+    Context(String),
+    /// `max-length=20`, `wrap`, `no-wrap` or a format flag like `no-c-format`
+    Flag(String),
+}
+
+/// The options of an `i18n:` comment, if it is one. Options that aren't understood are
+/// returned as errors with their text, to be reported.
+///
+/// Options are separated by commas, and a value can be quoted to contain one,
+/// e.g. `context="Save, then close"`.
+pub fn parse(comment: &Comment) -> Option<Vec<Result<Directive, String>>> {
+    let options = comment.text.trim().strip_prefix(PREFIX)?;
+    Some(
+        split_options(options)
+            .into_iter()
+            .map(parse_option)
+            .collect(),
+    )
+}
+
+/// Whether a comment is an `i18n:` comment, which shouldn't be extracted for translators
+pub fn is_directive(comment: &Comment) -> bool {
+    comment.text.trim().starts_with(PREFIX)
+}
+
+fn parse_option(option: &str) -> Result<Directive, String> {
+    let invalid = || option.to_string();
+    match option.split_once('=') {
+        Some((key, value)) => {
+            let value = unquote(value.trim());
+            match key.trim() {
+                "context" => Ok(Directive::Context(value.to_string())),
+                "max-length" => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|length| *length > 0)
+                    .map(|length| Directive::Flag(crate::flags::max_length_flag(length)))
+                    .ok_or_else(invalid),
+                _ => Err(invalid()),
+            }
+        }
+        None => match option {
+            "wrap" | "no-wrap" => Ok(Directive::Flag(option.to_string())),
+            flag if is_format_flag(flag) => Ok(Directive::Flag(flag.to_string())),
+            _ => Err(invalid()),
+        },
+    }
+}
+
+/// Whether an option is a format flag like `c-format` or `no-python-brace-format`
+fn is_format_flag(option: &str) -> bool {
+    let name = option.strip_prefix("no-").unwrap_or(option);
+    name.strip_suffix("-format").is_some_and(|language| {
+        !language.is_empty()
+            && language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '+')
+    })
+}
+
+/// Splits options on the commas that aren't in a quoted value. Only a quote right after the
+/// `=` starts a value, so that apostrophes can be used in unquoted values.
+fn split_options(text: &str) -> Vec<&str> {
+    let mut options = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    let mut previous = None;
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if previous == Some('=') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ',') => {
+                options.push(&text[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+        if !c.is_whitespace() {
+            previous = Some(c);
+        }
+    }
+    options.push(&text[start..]);
+    options
+        .into_iter()
+        .map(str::trim)
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| {
+            value
+                .strip_prefix(*quote)
+                .and_then(|value| value.strip_suffix(*quote))
+        })
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use swc_common::comments::CommentKind;
+
+    use super::*;
+
+    fn comment(text: &str) -> Comment {
+        Comment {
+            kind: CommentKind::Block,
+            span: Default::default(),
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn parses_options() {
+        assert_eq!(parse(&comment(" A translator comment ")), None);
+        assert_eq!(
+            parse(&comment(" i18n: context=toolbar, max-length=20, no-wrap ")),
+            Some(vec![
+                Ok(Directive::Context("toolbar".to_string())),
+                Ok(Directive::Flag("max-length:20".to_string())),
+                Ok(Directive::Flag("no-wrap".to_string())),
+            ])
+        );
+        assert_eq!(
+            parse(&comment(r#"i18n: context="Save, then close", no-c-format"#)),
+            Some(vec![
+                Ok(Directive::Context("Save, then close".to_string())),
+                Ok(Directive::Flag("no-c-format".to_string())),
+            ])
+        );
+        assert_eq!(
+            parse(&comment("i18n: context=user's menu, no-wrap")),
+            Some(vec![
+                Ok(Directive::Context("user's menu".to_string())),
+                Ok(Directive::Flag("no-wrap".to_string())),
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_invalid_options() {
+        assert_eq!(
+            parse(&comment(
+                "i18n: max-length=short, colour=red, shout, -format"
+            )),
+            Some(vec![
+                Err("max-length=short".to_string()),
+                Err("colour=red".to_string()),
+                Err("shout".to_string()),
+                Err("-format".to_string()),
+            ])
+        );
+    }
+}
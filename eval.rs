@@ -0,0 +1,179 @@
+use swc_common::{Span, Spanned};
+use swc_ecma_ast::*;
+
+/// Why an expression couldn't be evaluated to a static string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalErrorKind {
+    /// The expression isn't made of string literals, e.g. a variable or a function call
+    NonLiteral,
+    /// A template literal with `${}` expressions that aren't string literals themselves
+    InterpolatedTemplate,
+    /// A reference to a `let` or `var` binding, or to an object whose properties are assigned
+    MutableBinding,
+    /// A reference to an import from a project file that couldn't be found, with the imported path
+    UnresolvedImport(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvalError {
+    /// The part of the expression that couldn't be evaluated
+    pub span: Span,
+    pub kind: EvalErrorKind,
+}
+
+/// Provides the value of references like `CTX` or `MESSAGES.save` while evaluating
+pub trait Resolve {
+    fn resolve(&self, expr: &Expr) -> Result<String, EvalError>;
+}
+
+/// Doesn't resolve any reference, so that only literals are evaluated
+pub struct NoBindings;
+impl Resolve for NoBindings {
+    fn resolve(&self, expr: &Expr) -> Result<String, EvalError> {
+        Err(EvalError {
+            span: expr.span(),
+            kind: EvalErrorKind::NonLiteral,
+        })
+    }
+}
+
+/// Evaluates an expression to a string, if it only consists of string literals.
+///
+/// Supports `+` concatenation, parentheses, TypeScript wrappers (`as`, `satisfies`, `!`, `<T>`)
+/// and template literals whose expressions are static strings themselves.
+pub fn eval_string(expr: &Expr) -> Result<String, EvalError> {
+    eval_string_with(expr, &NoBindings)
+}
+
+/// Evaluates an expression to a string like [eval_string], looking up identifiers and
+/// member expressions with the given resolver
+pub fn eval_string_with(expr: &Expr, resolver: &dyn Resolve) -> Result<String, EvalError> {
+    match expr {
+        Expr::Lit(Lit::Str(Str { value, .. })) => Ok(value.to_string()),
+        Expr::Tpl(tpl) => eval_tpl(tpl, resolver),
+        Expr::Bin(BinExpr {
+            op: BinaryOp::Add,
+            left,
+            right,
+            ..
+        }) => {
+            let mut result = eval_string_with(left, resolver)?;
+            result.push_str(&eval_string_with(right, resolver)?);
+            Ok(result)
+        }
+        Expr::Ident(_) | Expr::Member(_) => resolver.resolve(expr),
+        Expr::Paren(_)
+        | Expr::TsAs(_)
+        | Expr::TsConstAssertion(_)
+        | Expr::TsSatisfies(_)
+        | Expr::TsNonNull(_)
+        | Expr::TsTypeAssertion(_) => eval_string_with(unwrap_expr(expr), resolver),
+        _ => Err(EvalError {
+            span: expr.span(),
+            kind: EvalErrorKind::NonLiteral,
+        }),
+    }
+}
+
+/// Removes parentheses and TypeScript wrappers that don't change the value of an expression
+pub fn unwrap_expr(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(ParenExpr { expr, .. })
+        | Expr::TsAs(TsAsExpr { expr, .. })
+        | Expr::TsConstAssertion(TsConstAssertion { expr, .. })
+        | Expr::TsSatisfies(TsSatisfiesExpr { expr, .. })
+        | Expr::TsNonNull(TsNonNullExpr { expr, .. })
+        | Expr::TsTypeAssertion(TsTypeAssertion { expr, .. }) => unwrap_expr(expr),
+        _ => expr,
+    }
+}
+
+fn eval_tpl(tpl: &Tpl, resolver: &dyn Resolve) -> Result<String, EvalError> {
+    let mut result = String::new();
+    for (index, quasi) in tpl.quasis.iter().enumerate() {
+        match &quasi.cooked {
+            Some(cooked) => result.push_str(cooked),
+            // Invalid escape sequences leave the template without a cooked value
+            None => {
+                return Err(EvalError {
+                    span: quasi.span,
+                    kind: EvalErrorKind::NonLiteral,
+                })
+            }
+        }
+        if let Some(expr) = tpl.exprs.get(index) {
+            match eval_string_with(expr, resolver) {
+                Ok(value) => result.push_str(&value),
+                Err(EvalError {
+                    kind: EvalErrorKind::NonLiteral,
+                    ..
+                }) => {
+                    return Err(EvalError {
+                        span: expr.span(),
+                        kind: EvalErrorKind::InterpolatedTemplate,
+                    })
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use swc_common::{sync::Lrc, FileName, SourceMap};
+    use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax, TsConfig};
+
+    use super::*;
+
+    fn eval(source: &str) -> Result<String, EvalErrorKind> {
+        let cm: Lrc<SourceMap> = Default::default();
+        let fm = cm.new_source_file(FileName::Custom("test.ts".into()), source.into());
+        let lexer = Lexer::new(
+            Syntax::Typescript(TsConfig::default()),
+            Default::default(),
+            StringInput::from(&*fm),
+            None,
+        );
+        let expr = Parser::new_from(lexer).parse_expr().unwrap();
+        eval_string(&expr).map_err(|e| e.kind)
+    }
+
+    #[test]
+    fn evaluates_concatenations() {
+        assert_eq!(
+            eval(r#"'first half ' + "second half""#),
+            Ok("first half second half".into())
+        );
+        assert_eq!(eval(r#"'a' + ('b' + `c`) + 'd'"#), Ok("abcd".into()));
+    }
+
+    #[test]
+    fn removes_typescript_wrappers() {
+        assert_eq!(eval(r#"('x')"#), Ok("x".into()));
+        assert_eq!(eval(r#"'x' as const"#), Ok("x".into()));
+        assert_eq!(eval(r#"'x' satisfies string"#), Ok("x".into()));
+        assert_eq!(eval(r#"'x'!"#), Ok("x".into()));
+        assert_eq!(eval(r#"<string>'x'"#), Ok("x".into()));
+        assert_eq!(eval(r#"('a' as string) + ('b'!)"#), Ok("ab".into()));
+    }
+
+    #[test]
+    fn joins_templates_with_static_expressions() {
+        assert_eq!(eval(r#"`a ${'b'} c ${`d` + 'e'}`"#), Ok("a b c de".into()));
+        assert_eq!(
+            eval(r#"`a ${b}`"#),
+            Err(EvalErrorKind::InterpolatedTemplate)
+        );
+    }
+
+    #[test]
+    fn rejects_non_literals() {
+        assert_eq!(eval(r#"message"#), Err(EvalErrorKind::NonLiteral));
+        assert_eq!(eval(r#"'a' + b"#), Err(EvalErrorKind::NonLiteral));
+        assert_eq!(eval(r#"'a' - 'b'"#), Err(EvalErrorKind::NonLiteral));
+        assert_eq!(eval(r#"1"#), Err(EvalErrorKind::NonLiteral));
+    }
+}
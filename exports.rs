@@ -0,0 +1,641 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use swc_common::{errors::Handler, sync::Lrc, FileName, SourceMap, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_parser::{lexer::Lexer, Parser, StringInput, Syntax};
+
+use crate::{constants::Constants, imports::module_export_name};
+
+/// Extensions tried when an import doesn't have one, in the order TypeScript tries them
+const EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx"];
+
+/// A string constant exported by a module, or an object literal of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportedValue {
+    String(String),
+    Object(BTreeMap<String, ExportedValue>),
+}
+impl ExportedValue {
+    /// Builds an expression with the same value, so that imported constants are resolved
+    /// like constants declared in the file
+    pub fn to_expr(&self) -> Expr {
+        match self {
+            ExportedValue::String(value) => Expr::Lit(Lit::Str(Str {
+                span: DUMMY_SP,
+                value: value.as_str().into(),
+                raw: None,
+            })),
+            ExportedValue::Object(values) => Expr::Object(ObjectLit {
+                span: DUMMY_SP,
+                props: values
+                    .iter()
+                    .map(|(key, value)| {
+                        PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                            key: PropName::Str(Str {
+                                span: DUMMY_SP,
+                                value: key.as_str().into(),
+                                raw: None,
+                            }),
+                            value: Box::new(value.to_expr()),
+                        })))
+                    })
+                    .collect(),
+            }),
+        }
+    }
+}
+
+/// The constants a module exports, by exported name
+pub type ModuleExports = HashMap<String, ExportedValue>;
+
+/// What the source of an import refers to
+#[derive(Debug, PartialEq, Eq)]
+pub enum ImportTarget<'a> {
+    /// A project file, with the constants it exports
+    Module(&'a ModuleExports),
+    /// A package, which isn't indexed
+    External,
+    /// A relative or tsconfig path import that doesn't match an indexed file
+    Unresolved,
+}
+
+/// The `baseUrl` and `paths` options of a tsconfig.json
+#[derive(Debug, Clone, Default)]
+pub struct TsConfigPaths {
+    /// Where non-relative imports are resolved from, if set
+    base_url: Option<PathBuf>,
+    /// Where the targets of `paths` are resolved from: the `baseUrl`, or the tsconfig.json folder
+    paths_base: PathBuf,
+    /// Patterns like `@i18n/*` and the paths they map to, like `src/i18n/*`
+    paths: Vec<(String, Vec<String>)>,
+}
+impl TsConfigPaths {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Self::parse(&source, dir).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Parses a tsconfig.json
+    fn parse(source: &str, dir: &Path) -> Result<Self, String> {
+        let config = parse_json(source, "tsconfig.json")?;
+
+        let compiler_options = object_prop(&config, "compilerOptions");
+        let base_url = match compiler_options.and_then(|options| object_prop(options, "baseUrl")) {
+            Some(Expr::Lit(Lit::Str(Str { value, .. }))) => Some(dir.join(value.as_str())),
+            Some(_) => return Err("`baseUrl` must be a string".to_string()),
+            None => None,
+        };
+
+        let mut paths = Vec::new();
+        match compiler_options.and_then(|options| object_prop(options, "paths")) {
+            Some(Expr::Object(ObjectLit { props, .. })) => {
+                for prop in props {
+                    let PropOrSpread::Prop(prop) = prop else {
+                        continue;
+                    };
+                    let Prop::KeyValue(KeyValueProp {
+                        key: PropName::Str(Str { value: pattern, .. }),
+                        value,
+                    }) = prop.as_ref()
+                    else {
+                        continue;
+                    };
+                    let Expr::Array(ArrayLit { elems, .. }) = value.as_ref() else {
+                        return Err(format!("`paths.{}` must be an array", pattern));
+                    };
+                    let targets = elems
+                        .iter()
+                        .flatten()
+                        .filter_map(|elem| match elem.expr.as_ref() {
+                            Expr::Lit(Lit::Str(Str { value, .. })) => Some(value.to_string()),
+                            _ => None,
+                        })
+                        .collect();
+                    paths.push((pattern.to_string(), targets));
+                }
+            }
+            Some(_) => return Err("`paths` must be an object".to_string()),
+            None => {}
+        }
+
+        Ok(Self {
+            paths_base: base_url.clone().unwrap_or_else(|| dir.to_path_buf()),
+            base_url,
+            paths,
+        })
+    }
+
+    /// The paths an import maps to, or `None` if no pattern matches it.
+    /// Exact patterns win over wildcards, then the wildcard with the longest prefix wins.
+    fn targets(&self, source: &str) -> Option<Vec<PathBuf>> {
+        let mut best: Option<(usize, &Vec<String>, &str)> = None;
+        for (pattern, targets) in &self.paths {
+            match pattern.split_once('*') {
+                None if pattern == source => {
+                    return Some(targets.iter().map(|t| self.paths_base.join(t)).collect())
+                }
+                None => {}
+                Some((prefix, suffix))
+                    if source.len() >= prefix.len() + suffix.len()
+                        && source.starts_with(prefix)
+                        && source.ends_with(suffix)
+                        && best.is_none_or(|(length, ..)| prefix.len() > length) =>
+                {
+                    let matched = &source[prefix.len()..source.len() - suffix.len()];
+                    best = Some((prefix.len(), targets, matched));
+                }
+                Some(_) => {}
+            }
+        }
+        best.map(|(_, targets, matched)| {
+            targets
+                .iter()
+                .map(|target| self.paths_base.join(target.replacen('*', matched, 1)))
+                .collect()
+        })
+    }
+}
+
+/// The string constants exported by every project file, so that gettext arguments can refer to
+/// constants imported from other files
+#[derive(Debug, Default)]
+pub struct ExportIndex {
+    modules: HashMap<PathBuf, ModuleExports>,
+    tsconfig: Option<TsConfigPaths>,
+}
+impl ExportIndex {
+    /// Indexes the exports of the given files. Files that can't be parsed export nothing,
+    /// their errors are reported when extracting.
+    pub fn build(files: &[PathBuf], tsconfig: Option<TsConfigPaths>) -> Self {
+        let mut index = Self {
+            modules: HashMap::new(),
+            tsconfig,
+        };
+
+        let mut infos = HashMap::new();
+        for file in files {
+            let Ok(path) = file.canonicalize() else {
+                continue;
+            };
+            let cm: Lrc<SourceMap> = Default::default();
+            let handler = Handler::with_emitter_writer(Box::new(std::io::sink()), None);
+            if let Some(module) = crate::walker::load_module(file, &cm, &handler, None) {
+                let info = index.module_info(&path, &module);
+                infos.insert(path, info);
+            }
+        }
+
+        let mut modules = HashMap::new();
+        for path in infos.keys() {
+            collect_exports(path, &infos, &mut modules, &mut HashSet::new());
+        }
+        index.modules = modules;
+        index
+    }
+
+    /// Finds what the source of an import in a file refers to
+    pub fn resolve(&self, from: &Path, source: &str) -> ImportTarget<'_> {
+        match self.resolve_file(from, source) {
+            Some(Ok(path)) => match self.modules.get(&path) {
+                Some(exports) => ImportTarget::Module(exports),
+                None => ImportTarget::Unresolved,
+            },
+            Some(Err(())) => ImportTarget::Unresolved,
+            None => ImportTarget::External,
+        }
+    }
+
+    /// Finds the file an import refers to. Returns `None` for packages, and an error when the
+    /// import looks like a project file that doesn't exist.
+    fn resolve_file(&self, from: &Path, source: &str) -> Option<Result<PathBuf, ()>> {
+        if source.starts_with("./") || source.starts_with("../") || source == "." || source == ".."
+        {
+            let dir = from.parent().unwrap_or(Path::new("."));
+            return Some(find_file(&dir.join(source)).ok_or(()));
+        }
+        if source.starts_with('/') {
+            return Some(find_file(Path::new(source)).ok_or(()));
+        }
+
+        let tsconfig = self.tsconfig.as_ref()?;
+        if let Some(targets) = tsconfig.targets(source) {
+            return Some(
+                targets
+                    .iter()
+                    .find_map(|target| find_file(target))
+                    .ok_or(()),
+            );
+        }
+        tsconfig
+            .base_url
+            .as_ref()
+            .and_then(|base_url| find_file(&base_url.join(source)))
+            .map(Ok)
+    }
+
+    /// Finds the constants and re-exports of a module
+    fn module_info(&self, path: &Path, module: &Module) -> ModuleInfo {
+        let mut constants = Constants::default();
+        constants.declare_module(module, None);
+
+        // Local names bound by imports, for `import { A } from './a'; export { A }`
+        let mut imports = HashMap::<String, (String, String)>::new();
+        for item in &module.body {
+            if let ModuleItem::ModuleDecl(ModuleDecl::Import(import)) = item {
+                for specifier in &import.specifiers {
+                    if let ImportSpecifier::Named(ImportNamedSpecifier {
+                        local, imported, ..
+                    }) = specifier
+                    {
+                        let imported = imported
+                            .as_ref()
+                            .map(module_export_name)
+                            .unwrap_or_else(|| local.sym.to_string());
+                        imports.insert(
+                            local.sym.to_string(),
+                            (import.src.value.to_string(), imported),
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut info = ModuleInfo::default();
+        let mut re_export = |source: &str, export| {
+            if let Some(Ok(path)) = self.resolve_file(path, source) {
+                info.re_exports.push((path, export));
+            }
+        };
+        let mut values = ModuleExports::new();
+        for item in &module.body {
+            let ModuleItem::ModuleDecl(decl) = item else {
+                continue;
+            };
+            match decl {
+                // export const A = 'a';
+                ModuleDecl::ExportDecl(ExportDecl {
+                    decl: Decl::Var(var),
+                    ..
+                }) => {
+                    for declarator in &var.decls {
+                        if let Pat::Ident(BindingIdent { id, .. }) = &declarator.name {
+                            if let Some(value) = constants.exported_value(&id.sym) {
+                                values.insert(id.sym.to_string(), value);
+                            }
+                        }
+                    }
+                }
+                // export { A, B as C };
+                ModuleDecl::ExportNamed(NamedExport {
+                    specifiers,
+                    src: None,
+                    ..
+                }) => {
+                    for specifier in specifiers {
+                        let ExportSpecifier::Named(ExportNamedSpecifier { orig, exported, .. }) =
+                            specifier
+                        else {
+                            continue;
+                        };
+                        let local = module_export_name(orig);
+                        let exported = exported
+                            .as_ref()
+                            .map(module_export_name)
+                            .unwrap_or_else(|| local.clone());
+                        match imports.get(&local) {
+                            Some((source, imported)) => {
+                                re_export(source, ReExport::Named(imported.clone(), exported))
+                            }
+                            None => {
+                                if let Some(value) = constants.exported_value(&local) {
+                                    values.insert(exported, value);
+                                }
+                            }
+                        }
+                    }
+                }
+                // export { A, B as C } from './a'; export * as a from './a';
+                ModuleDecl::ExportNamed(NamedExport {
+                    specifiers,
+                    src: Some(src),
+                    ..
+                }) => {
+                    for specifier in specifiers {
+                        match specifier {
+                            ExportSpecifier::Named(ExportNamedSpecifier {
+                                orig, exported, ..
+                            }) => {
+                                let imported = module_export_name(orig);
+                                let exported = exported
+                                    .as_ref()
+                                    .map(module_export_name)
+                                    .unwrap_or_else(|| imported.clone());
+                                re_export(&src.value, ReExport::Named(imported, exported))
+                            }
+                            ExportSpecifier::Namespace(ExportNamespaceSpecifier {
+                                name, ..
+                            }) => {
+                                re_export(&src.value, ReExport::Namespace(module_export_name(name)))
+                            }
+                            ExportSpecifier::Default(_) => {}
+                        }
+                    }
+                }
+                // export * from './a';
+                ModuleDecl::ExportAll(ExportAll { src, .. }) => {
+                    re_export(&src.value, ReExport::All)
+                }
+                _ => {}
+            }
+        }
+        info.values = values;
+        info
+    }
+}
+
+/// What a module exports before re-exports are followed
+#[derive(Debug, Default)]
+struct ModuleInfo {
+    values: ModuleExports,
+    /// The resolved source of each re-export
+    re_exports: Vec<(PathBuf, ReExport)>,
+}
+
+#[derive(Debug)]
+enum ReExport {
+    /// `export * from './a'`
+    All,
+    /// `export { a as b } from './a'`, with the imported and exported names
+    Named(String, String),
+    /// `export * as a from './a'`
+    Namespace(String),
+}
+
+/// Follows the re-exports of a module to find everything it exports
+fn collect_exports(
+    path: &Path,
+    infos: &HashMap<PathBuf, ModuleInfo>,
+    modules: &mut HashMap<PathBuf, ModuleExports>,
+    visiting: &mut HashSet<PathBuf>,
+) -> ModuleExports {
+    if let Some(exports) = modules.get(path) {
+        return exports.clone();
+    }
+    let Some(info) = infos.get(path) else {
+        return ModuleExports::new();
+    };
+    // Modules that re-export each other only see what's been found so far
+    if !visiting.insert(path.to_path_buf()) {
+        return info.values.clone();
+    }
+
+    let mut exports = ModuleExports::new();
+    for (source, re_export) in &info.re_exports {
+        let other = collect_exports(source, infos, modules, visiting);
+        match re_export {
+            ReExport::All => {
+                for (name, value) in other {
+                    // `export *` doesn't include the default export
+                    if name != "default" {
+                        exports.insert(name, value);
+                    }
+                }
+            }
+            ReExport::Named(imported, exported) => {
+                if let Some(value) = other.get(imported) {
+                    exports.insert(exported.clone(), value.clone());
+                }
+            }
+            ReExport::Namespace(exported) => {
+                exports.insert(
+                    exported.clone(),
+                    ExportedValue::Object(other.into_iter().collect()),
+                );
+            }
+        }
+    }
+    exports.extend(info.values.clone());
+
+    visiting.remove(path);
+    modules.insert(path.to_path_buf(), exports.clone());
+    exports
+}
+
+/// Finds the source file an import path refers to, like TypeScript does: as is, with an
+/// extension, with a `.ts` file for a `.js` import, or as a folder with an index file
+fn find_file(path: &Path) -> Option<PathBuf> {
+    let has_source_extension = path
+        .extension()
+        .is_some_and(|ext| EXTENSIONS.iter().any(|candidate| ext == *candidate));
+
+    let mut candidates = Vec::new();
+    if has_source_extension {
+        candidates.push(path.to_path_buf());
+        // `import './a.js'` in TypeScript refers to `./a.ts`
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("js") => candidates.push(path.with_extension("ts")),
+            Some("jsx") => candidates.push(path.with_extension("tsx")),
+            _ => {}
+        }
+    }
+    for extension in EXTENSIONS {
+        let mut candidate = OsString::from(path);
+        candidate.push(".");
+        candidate.push(extension);
+        candidates.push(candidate.into());
+    }
+    for extension in EXTENSIONS {
+        candidates.push(path.join("index").with_extension(extension));
+    }
+
+    candidates
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| candidate.canonicalize().ok())
+}
+
+/// Parses a JSON config file. It is read as a JavaScript expression, as config files like
+/// tsconfig.json allow comments and trailing commas.
+pub fn parse_json(source: &str, name: &str) -> Result<Box<Expr>, String> {
+    let cm: Lrc<SourceMap> = Default::default();
+    let fm = cm.new_source_file(FileName::Custom(name.into()), format!("({})", source));
+    let lexer = Lexer::new(
+        Syntax::Es(Default::default()),
+        EsVersion::EsNext,
+        StringInput::from(&*fm),
+        None,
+    );
+    Parser::new_from(lexer)
+        .parse_expr()
+        .map_err(|e| e.kind().msg().to_string())
+}
+
+/// The value of a property of an object literal, if the expression is one
+pub fn object_prop<'a>(expr: &'a Expr, name: &str) -> Option<&'a Expr> {
+    let Expr::Object(ObjectLit { props, .. }) = unwrap_paren(expr) else {
+        return None;
+    };
+    props.iter().rev().find_map(|prop| match prop {
+        PropOrSpread::Prop(prop) => match prop.as_ref() {
+            Prop::KeyValue(KeyValueProp {
+                key: PropName::Str(Str { value, .. }),
+                value: prop_value,
+            }) if value == name => Some(prop_value.as_ref()),
+            Prop::KeyValue(KeyValueProp {
+                key: PropName::Ident(Ident { sym, .. }),
+                value: prop_value,
+            }) if sym == name => Some(prop_value.as_ref()),
+            _ => None,
+        },
+        PropOrSpread::Spread(_) => None,
+    })
+}
+
+fn unwrap_paren(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(ParenExpr { expr, .. }) => unwrap_paren(expr),
+        _ => expr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parses_tsconfig_with_comments() {
+        let tsconfig = TsConfigPaths::parse(
+            r#"{
+                // Comments and trailing commas are allowed
+                "compilerOptions": {
+                    "baseUrl": "./src",
+                    "paths": {
+                        "@i18n/*": ["i18n/*", "legacy/i18n/*"],
+                        "@constants": ["i18n/constants.ts"], /* exact */
+                    },
+                },
+            }"#,
+            Path::new("project"),
+        )
+        .unwrap();
+        assert_eq!(tsconfig.base_url, Some(PathBuf::from("project/./src")));
+        assert_eq!(
+            tsconfig.paths,
+            vec![
+                (
+                    "@i18n/*".to_string(),
+                    vec!["i18n/*".to_string(), "legacy/i18n/*".to_string()]
+                ),
+                (
+                    "@constants".to_string(),
+                    vec!["i18n/constants.ts".to_string()]
+                ),
+            ]
+        );
+        assert!(
+            TsConfigPaths::parse(r#"{ "compilerOptions": { "paths": [] } }"#, Path::new("."))
+                .is_err()
+        );
+        assert!(TsConfigPaths::parse(r#"{ "#, Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn matches_tsconfig_paths() {
+        let tsconfig = TsConfigPaths {
+            base_url: None,
+            paths_base: PathBuf::from("root"),
+            paths: vec![
+                ("@app/*".to_string(), vec!["src/*".to_string()]),
+                (
+                    "@app/i18n/*".to_string(),
+                    vec!["src/translations/*".to_string()],
+                ),
+                (
+                    "@app/i18n/messages".to_string(),
+                    vec!["src/messages".to_string()],
+                ),
+                ("*.css".to_string(), vec!["styles/*.css".to_string()]),
+            ],
+        };
+        assert_eq!(
+            tsconfig.targets("@app/components/button"),
+            Some(vec![PathBuf::from("root/src/components/button")])
+        );
+        assert_eq!(
+            tsconfig.targets("@app/i18n/constants"),
+            Some(vec![PathBuf::from("root/src/translations/constants")])
+        );
+        assert_eq!(
+            tsconfig.targets("@app/i18n/messages"),
+            Some(vec![PathBuf::from("root/src/messages")])
+        );
+        assert_eq!(
+            tsconfig.targets("theme.css"),
+            Some(vec![PathBuf::from("root/styles/theme.css")])
+        );
+        assert_eq!(tsconfig.targets("react"), None);
+    }
+
+    #[test]
+    fn indexes_exported_constants() {
+        let files = crate::walker::find_ts_files(PathBuf::from("./tests/imports"), Vec::new())
+            .unwrap()
+            .map(|entry| entry.into_path())
+            .collect::<Vec<_>>();
+        let tsconfig = TsConfigPaths::load(Path::new("./tests/imports/tsconfig.json")).unwrap();
+        let index = ExportIndex::build(&files, Some(tsconfig));
+        let from = Path::new("./tests/imports/src/app.ts");
+
+        let ImportTarget::Module(constants) = index.resolve(from, "./i18n/constants") else {
+            panic!("Failed to resolve ./i18n/constants");
+        };
+        assert_eq!(
+            constants.get("DOMAIN"),
+            Some(&ExportedValue::String("billing".to_string()))
+        );
+        assert_eq!(
+            constants.get("MESSAGES"),
+            Some(&ExportedValue::Object(BTreeMap::from([
+                (
+                    "pay".to_string(),
+                    ExportedValue::String("Pay now".to_string())
+                ),
+                (
+                    "save".to_string(),
+                    ExportedValue::String("Save".to_string())
+                ),
+            ])))
+        );
+        assert_eq!(constants.get("format"), None);
+
+        // Resolved through tsconfig paths and `index.ts` re-exports
+        let ImportTarget::Module(reexported) = index.resolve(from, "@i18n") else {
+            panic!("Failed to resolve @i18n");
+        };
+        assert_eq!(
+            reexported.get("CHECKOUT"),
+            Some(&ExportedValue::String("checkout".to_string()))
+        );
+        assert_eq!(reexported.get("DOMAIN"), constants.get("DOMAIN"));
+        assert_eq!(
+            index.resolve(from, "@i18n/constants.js"),
+            ImportTarget::Module(constants)
+        );
+
+        assert_eq!(index.resolve(from, "./missing"), ImportTarget::Unresolved);
+        assert_eq!(
+            index.resolve(from, "@i18n/missing"),
+            ImportTarget::Unresolved
+        );
+        assert_eq!(index.resolve(from, "react"), ImportTarget::External);
+    }
+}
@@ -0,0 +1,338 @@
+// See https://www.gnu.org/software/gettext/manual/html_node/Sticky-flags.html for the flags
+// translation tools use to check placeholders
+
+use std::collections::BTreeSet;
+
+use swc_common::comments::Comment;
+
+use crate::pot::POTMessageID;
+
+pub const C_FORMAT: &str = "c-format";
+pub const JAVASCRIPT_FORMAT: &str = "javascript-format";
+pub const PYTHON_BRACE_FORMAT: &str = "python-brace-format";
+pub const ICU_FORMAT: &str = "icu-format";
+
+const DIRECTIVE: &str = "xgettext:";
+const MAX_LENGTH: &str = "max-length:";
+
+/// ICU argument types, as in `{count, plural, ...}`
+const ICU_TYPES: &[&str] = &[
+    "plural",
+    "select",
+    "selectordinal",
+    "number",
+    "date",
+    "time",
+    "duration",
+    "spellout",
+    "ordinal",
+];
+
+/// The format flags of the placeholders found in a message's msgid and plural
+pub fn detect_message(id: &POTMessageID) -> BTreeSet<String> {
+    std::iter::once(&id.msgid)
+        .chain(&id.msgid_plural)
+        .flat_map(|text| detect(text))
+        .map(String::from)
+        .collect()
+}
+
+/// The format flags of the placeholders found in a string:
+///
+/// - `c-format` for printf directives like `%d`, `%s` or `%1$s`
+/// - `javascript-format` for template placeholders like `${0}` or `${name}`
+/// - `icu-format` for ICU arguments with a type, like `{count, plural, ...}`
+/// - `python-brace-format` for `{name}` or `{0}`, unless the string is an ICU message
+pub fn detect(text: &str) -> Vec<&'static str> {
+    let mut flags = Vec::new();
+    if has_printf_directive(text) {
+        flags.push(C_FORMAT);
+    }
+    if has_template_placeholder(text) {
+        flags.push(JAVASCRIPT_FORMAT);
+    }
+    if has_icu_argument(text) {
+        // Simple ICU arguments look like brace placeholders
+        flags.push(ICU_FORMAT);
+    } else if has_brace_placeholder(text) {
+        flags.push(PYTHON_BRACE_FORMAT);
+    }
+    flags
+}
+
+/// The flags set by an `xgettext:` comment like `/* xgettext:no-c-format */`, if it is one.
+/// Several flags can be separated by commas.
+pub fn parse_directive(comment: &Comment) -> Option<Vec<&str>> {
+    let flags = comment.text.trim().strip_prefix(DIRECTIVE)?;
+    Some(
+        flags
+            .split([',', ' ', '\t'])
+            .filter(|flag| flag.ends_with("-format"))
+            .collect(),
+    )
+}
+
+/// Applies flags from an `xgettext:` comment to the flags detected for a call, so that
+/// `no-c-format` replaces `c-format` and the other way around
+pub fn apply_directive(flags: &mut BTreeSet<String>, directive: &[&str]) {
+    for flag in directive {
+        if max_length(flag).is_some() {
+            flags.retain(|flag| max_length(flag).is_none());
+        }
+        match flag.strip_prefix("no-") {
+            Some(positive) => flags.remove(positive),
+            None => flags.remove(&format!("no-{}", flag)),
+        };
+        flags.insert(flag.to_string());
+    }
+}
+
+/// Adds a flag to a message that can be found in several calls. A `no-` flag from any call wins
+/// over the detected flag, and the shortest `max-length` wins over longer ones.
+pub fn insert(flags: &mut BTreeSet<String>, flag: String) {
+    if let Some(length) = max_length(&flag) {
+        if let Some(existing) = flags.iter().find_map(|flag| max_length(flag)) {
+            if existing <= length {
+                return;
+            }
+            flags.retain(|flag| max_length(flag).is_none());
+        }
+    }
+    match flag.strip_prefix("no-") {
+        Some(positive) => {
+            flags.remove(positive);
+        }
+        None if flags.contains(&format!("no-{}", flag)) => return,
+        None => {}
+    }
+    flags.insert(flag);
+}
+
+/// The flag limiting the length of translations, as used by Weblate and Pootle
+pub fn max_length_flag(length: usize) -> String {
+    format!("{}{}", MAX_LENGTH, length)
+}
+
+fn max_length(flag: &str) -> Option<usize> {
+    flag.strip_prefix(MAX_LENGTH)?.parse().ok()
+}
+
+/// Whether the string has a printf directive. The space flag isn't accepted, so that
+/// text like `100% sure` isn't taken for a directive.
+fn has_printf_directive(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] != b'%' {
+            index += 1;
+            continue;
+        }
+        index += 1;
+        if bytes.get(index) == Some(&b'%') {
+            index += 1;
+            continue;
+        }
+        let digits = |index: &mut usize| {
+            while bytes.get(*index).is_some_and(u8::is_ascii_digit) {
+                *index += 1;
+            }
+        };
+        let start = index;
+        // Argument position, like `%1$s`
+        digits(&mut index);
+        if bytes.get(index) != Some(&b'$') {
+            index = start;
+        } else if index > start {
+            index += 1;
+        }
+        while bytes.get(index).is_some_and(|byte| b"-+0#'".contains(byte)) {
+            index += 1;
+        }
+        if bytes.get(index) == Some(&b'*') {
+            index += 1;
+        } else {
+            digits(&mut index);
+        }
+        if bytes.get(index) == Some(&b'.') {
+            index += 1;
+            if bytes.get(index) == Some(&b'*') {
+                index += 1;
+            } else {
+                digits(&mut index);
+            }
+        }
+        while bytes
+            .get(index)
+            .is_some_and(|byte| b"hlLqjzt".contains(byte))
+        {
+            index += 1;
+        }
+        if bytes
+            .get(index)
+            .is_some_and(|byte| b"diouxXeEfFgGaAcsp".contains(byte))
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether the string has a `${}` placeholder, as written for tagged templates
+fn has_template_placeholder(text: &str) -> bool {
+    text.match_indices("${").any(|(index, _)| {
+        let after = &text[index + 2..];
+        placeholder_name(after).is_some_and(|name| after[name.len()..].starts_with('}'))
+    })
+}
+
+/// Whether the string has a `{name}` placeholder. `{{` and `${` don't start one.
+fn has_brace_placeholder(text: &str) -> bool {
+    let mut index = 0;
+    while let Some(found) = text[index..].find('{') {
+        let start = index + found;
+        let after = &text[start + 1..];
+        index = start + 1;
+        if after.starts_with('{') {
+            index += 1;
+            continue;
+        }
+        if text[..start].ends_with('$') {
+            continue;
+        }
+        let Some(name) = placeholder_name(after) else {
+            continue;
+        };
+        // A conversion or format spec can follow the field name, like `{0!r}` or `{price:.2f}`
+        let tail = &after[name.len()..];
+        if tail.starts_with('}')
+            || (tail.starts_with([':', '!'])
+                && tail
+                    .find(['{', '}'])
+                    .is_some_and(|end| tail[end..].starts_with('}')))
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether the string has an ICU argument with a type, like `{count, plural, ...}`
+fn has_icu_argument(text: &str) -> bool {
+    text.match_indices('{').any(|(index, _)| {
+        let after = text[index + 1..].trim_start();
+        let Some(name) = placeholder_name(after) else {
+            return false;
+        };
+        let Some(tail) = after[name.len()..].trim_start().strip_prefix(',') else {
+            return false;
+        };
+        let tail = tail.trim_start();
+        let kind = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .map_or(tail, |end| &tail[..end]);
+        ICU_TYPES.contains(&kind)
+    })
+}
+
+/// The identifier or number at the start of a placeholder
+fn placeholder_name(text: &str) -> Option<&str> {
+    let end = text
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(text.len());
+    let name = &text[..end];
+    match name.is_empty() || name.starts_with('$') {
+        true => None,
+        false => Some(name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn detects_printf_directives() {
+        assert_eq!(detect("You won %d coffees."), vec![C_FORMAT]);
+        assert_eq!(detect("Hello %s"), vec![C_FORMAT]);
+        assert_eq!(detect("%2$s by %1$s"), vec![C_FORMAT]);
+        assert_eq!(detect("%-10.2lf"), vec![C_FORMAT]);
+        assert_eq!(detect("100%% sure"), Vec::<&str>::new());
+        assert_eq!(detect("100% sure"), Vec::<&str>::new());
+        assert_eq!(detect("Trailing %"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn detects_template_placeholders() {
+        assert_eq!(detect("Hello ${0}"), vec![JAVASCRIPT_FORMAT]);
+        assert_eq!(detect("Hello ${name}"), vec![JAVASCRIPT_FORMAT]);
+        assert_eq!(detect("Costs $5"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn detects_brace_placeholders() {
+        assert_eq!(detect("Hello {name}"), vec![PYTHON_BRACE_FORMAT]);
+        assert_eq!(detect("{0} of {1}"), vec![PYTHON_BRACE_FORMAT]);
+        assert_eq!(detect("Total: {price:.2f}"), vec![PYTHON_BRACE_FORMAT]);
+        assert_eq!(detect("Use {{name}} for names"), Vec::<&str>::new());
+        assert_eq!(detect("{ not a placeholder }"), Vec::<&str>::new());
+        assert_eq!(detect("{\"json\": 1}"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn detects_icu_arguments() {
+        assert_eq!(
+            detect("{count, plural, one {# file} other {# files}} in {folder}"),
+            vec![ICU_FORMAT]
+        );
+        assert_eq!(detect("{ gender , select, other {them}}"), vec![ICU_FORMAT]);
+        assert_eq!(detect("{name, unknown}"), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn detects_several_formats() {
+        assert_eq!(detect("%d ${0}"), vec![C_FORMAT, JAVASCRIPT_FORMAT]);
+        assert_eq!(
+            detect_message(&POTMessageID {
+                msgctx: None,
+                msgid: "One file".to_string(),
+                msgid_plural: Some("%d files".to_string()),
+            }),
+            BTreeSet::from([C_FORMAT.to_string()])
+        );
+    }
+
+    #[test]
+    fn applies_directives() {
+        let comment = |text: &str| Comment {
+            kind: swc_common::comments::CommentKind::Block,
+            span: Default::default(),
+            text: text.into(),
+        };
+        assert_eq!(parse_directive(&comment(" TRANSLATORS: hi ")), None);
+        let directive = comment(" xgettext:no-c-format, python-brace-format ");
+        let directive = parse_directive(&directive).unwrap();
+        assert_eq!(directive, vec!["no-c-format", "python-brace-format"]);
+
+        let mut flags = BTreeSet::from([C_FORMAT.to_string()]);
+        apply_directive(&mut flags, &directive);
+        assert_eq!(
+            flags,
+            BTreeSet::from(["no-c-format".to_string(), PYTHON_BRACE_FORMAT.to_string()])
+        );
+
+        // A `no-` flag from one call wins over the flag detected in another
+        let mut flags = BTreeSet::new();
+        insert(&mut flags, "no-c-format".to_string());
+        insert(&mut flags, C_FORMAT.to_string());
+        assert_eq!(flags, BTreeSet::from(["no-c-format".to_string()]));
+
+        // So does the shortest maximum length
+        let mut flags = BTreeSet::new();
+        insert(&mut flags, max_length_flag(30));
+        insert(&mut flags, max_length_flag(20));
+        insert(&mut flags, max_length_flag(25));
+        assert_eq!(flags, BTreeSet::from(["max-length:20".to_string()]));
+    }
+}
@@ -0,0 +1,556 @@
+// See https://www.gnu.org/software/gettext/manual/html_node/Header-Entry.html for the fields of
+// the header entry
+
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use swc_ecma_ast::*;
+
+use crate::{
+    exports::{object_prop, parse_json},
+    po,
+};
+
+/// The plural rule of English, written unless `--no-plural-forms` is used
+pub const DEFAULT_PLURAL_FORMS: &str = "nplurals=2; plural=(n != 1);";
+/// Files are always written in UTF-8
+const CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+/// Written next to `Content-Type` unless they are overridden, like xgettext does
+const DEFAULT_MIME_VERSION: &str = "1.0";
+const DEFAULT_CONTENT_TRANSFER_ENCODING: &str = "8bit";
+/// The fields that can be set besides custom `X-` fields
+const STANDARD_FIELDS: &[&str] = &[
+    "Project-Id-Version",
+    "Report-Msgid-Bugs-To",
+    "POT-Creation-Date",
+    "PO-Revision-Date",
+    "Last-Translator",
+    "Language-Team",
+    "Language",
+    "MIME-Version",
+    "Content-Transfer-Encoding",
+    "Plural-Forms",
+];
+
+/// The header entry of a POT file, the msgstr of the empty msgid, and the comment before it.
+/// By default it only has the MIME fields and `Plural-Forms`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    /// Comment lines written before the header, like a title and a copyright notice
+    pub comments: Vec<String>,
+    pub project_id_version: Option<String>,
+    pub report_msgid_bugs_to: Option<String>,
+    pub pot_creation_date: Option<String>,
+    pub po_revision_date: Option<String>,
+    pub last_translator: Option<String>,
+    pub language_team: Option<String>,
+    pub language: Option<String>,
+    pub mime_version: Option<String>,
+    pub content_transfer_encoding: Option<String>,
+    /// `None` to leave the plural rule to translators, as it depends on the language
+    pub plural_forms: Option<String>,
+    /// Custom fields like `X-Generator`, in the order they were set
+    pub custom: Vec<(String, String)>,
+}
+impl Default for Header {
+    fn default() -> Self {
+        Self {
+            comments: Vec::new(),
+            project_id_version: None,
+            report_msgid_bugs_to: None,
+            pot_creation_date: None,
+            po_revision_date: None,
+            last_translator: None,
+            language_team: None,
+            language: None,
+            mime_version: Some(DEFAULT_MIME_VERSION.to_string()),
+            content_transfer_encoding: Some(DEFAULT_CONTENT_TRANSFER_ENCODING.to_string()),
+            plural_forms: Some(DEFAULT_PLURAL_FORMS.to_string()),
+            custom: Vec::new(),
+        }
+    }
+}
+impl Header {
+    /// Builds the header from the options, at the given time for `POT-Creation-Date` and the
+    /// copyright year
+    pub fn new(options: &HeaderOptions, now: SystemTime) -> Self {
+        let now = DateTime::from(now);
+
+        let mut comments = options
+            .comments
+            .iter()
+            .flat_map(|comment| comment.split('\n'))
+            .map(String::from)
+            .collect::<Vec<_>>();
+        if let Some(holder) = &options.copyright_holder {
+            comments.push(format!("Copyright (C) {} {}", now.year, holder));
+        }
+        if let Some(name) = &options.package_name {
+            comments.push(format!(
+                "This file is distributed under the same license as the {} package.",
+                name
+            ));
+        }
+
+        let project = [&options.package_name, &options.package_version]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let mut header = Self {
+            comments,
+            project_id_version: Some(project.join(" ")).filter(|_| !project.is_empty()),
+            report_msgid_bugs_to: options.msgid_bugs_address.clone(),
+            pot_creation_date: options
+                .pot_creation_date
+                .as_ref()
+                .map(|date| match date.as_str() {
+                    "now" => now.to_string(),
+                    date => date.to_string(),
+                }),
+            plural_forms: match options.no_plural_forms {
+                true => None,
+                false => Some(
+                    options
+                        .plural_forms
+                        .clone()
+                        .unwrap_or(DEFAULT_PLURAL_FORMS.to_string()),
+                ),
+            },
+            ..Self::default()
+        };
+        for field in &options.fields {
+            header.set(field);
+        }
+        header
+    }
+
+    /// Sets a field by its name. Custom fields replace the field with the same name.
+    fn set(&mut self, field: &HeaderField) {
+        let value = Some(field.value.clone());
+        match field.name.as_str() {
+            "Project-Id-Version" => self.project_id_version = value,
+            "Report-Msgid-Bugs-To" => self.report_msgid_bugs_to = value,
+            "POT-Creation-Date" => self.pot_creation_date = value,
+            "PO-Revision-Date" => self.po_revision_date = value,
+            "Last-Translator" => self.last_translator = value,
+            "Language-Team" => self.language_team = value,
+            "Language" => self.language = value,
+            "MIME-Version" => self.mime_version = value,
+            "Content-Transfer-Encoding" => self.content_transfer_encoding = value,
+            "Plural-Forms" => self.plural_forms = value,
+            name => match self.custom.iter_mut().find(|(custom, _)| custom == name) {
+                Some((_, existing)) => existing.clone_from(&field.value),
+                None => self.custom.push((name.to_string(), field.value.clone())),
+            },
+        }
+    }
+
+    /// The fields that are set, in the order xgettext writes them, then the custom fields
+    pub fn fields(&self) -> Vec<(&str, &str)> {
+        [
+            ("Project-Id-Version", self.project_id_version.as_deref()),
+            ("Report-Msgid-Bugs-To", self.report_msgid_bugs_to.as_deref()),
+            ("POT-Creation-Date", self.pot_creation_date.as_deref()),
+            ("PO-Revision-Date", self.po_revision_date.as_deref()),
+            ("Last-Translator", self.last_translator.as_deref()),
+            ("Language-Team", self.language_team.as_deref()),
+            ("Language", self.language.as_deref()),
+            ("MIME-Version", self.mime_version.as_deref()),
+            ("Content-Type", Some(CONTENT_TYPE)),
+            (
+                "Content-Transfer-Encoding",
+                self.content_transfer_encoding.as_deref(),
+            ),
+            ("Plural-Forms", self.plural_forms.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .chain(
+            self.custom
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        )
+        .collect()
+    }
+
+    /// The msgstr of the header entry, with a line per field
+    pub fn msgstr(&self) -> String {
+        self.fields()
+            .into_iter()
+            .map(|(name, value)| format!("{}: {}\n", name, value))
+            .collect()
+    }
+}
+
+/// The `POT-Creation-Date` in the header of an existing POT file, if it has one
+pub fn creation_date(pot: &str) -> Option<String> {
+    po::parse(pot)
+        .ok()?
+        .header_field("POT-Creation-Date")
+        .map(String::from)
+}
+
+/// A `Name=value` header field, as passed to `--header`. The name is a standard field, or a
+/// custom field starting with `X-`. `Content-Type` can't be set, as files are always UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderField {
+    pub name: String,
+    pub value: String,
+}
+impl HeaderField {
+    fn new(name: &str, value: &str) -> Result<Self, String> {
+        let name = name.trim();
+        match name {
+            "Content-Type" => {
+                Err("`Content-Type` can't be changed, files are written in UTF-8".to_string())
+            }
+            _ if STANDARD_FIELDS.contains(&name) => Ok(()),
+            _ if name.starts_with("X-")
+                && name.len() > 2
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                Ok(())
+            }
+            _ => Err(format!(
+                "`{}` isn't a standard header field, custom fields start with `X-`",
+                name
+            )),
+        }?;
+        if value.contains('\n') {
+            return Err(format!("The value of `{}` can't have line breaks", name));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+}
+impl FromStr for HeaderField {
+    type Err = String;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        let (name, value) = field
+            .split_once('=')
+            .ok_or_else(|| format!("Expected `Name=value`, got `{}`", field))?;
+        Self::new(name, value)
+    }
+}
+
+/// The header options, from the command line or the `header` object of a config file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderOptions {
+    /// Comment lines, like a title
+    pub comments: Vec<String>,
+    pub copyright_holder: Option<String>,
+    pub package_name: Option<String>,
+    pub package_version: Option<String>,
+    pub msgid_bugs_address: Option<String>,
+    /// A date, or `now` for the time of extraction
+    pub pot_creation_date: Option<String>,
+    pub plural_forms: Option<String>,
+    pub no_plural_forms: bool,
+    pub fields: Vec<HeaderField>,
+}
+impl HeaderOptions {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&source).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    /// Parses the `header` object of a JSON config file, like
+    /// `{ "header": { "packageName": "app", "fields": { "X-Generator": "app" } } }`
+    fn parse(source: &str) -> Result<Self, String> {
+        let config = parse_json(source, "config.json")?;
+        let Some(header) = object_prop(&config, "header") else {
+            return Ok(Self::default());
+        };
+        let string = |name: &str| match object_prop(header, name) {
+            Some(Expr::Lit(Lit::Str(Str { value, .. }))) => Ok(Some(value.to_string())),
+            Some(_) => Err(format!("`header.{}` must be a string", name)),
+            None => Ok(None),
+        };
+
+        let comments = match object_prop(header, "comments") {
+            Some(Expr::Array(ArrayLit { elems, .. })) => elems
+                .iter()
+                .map(|elem| match elem.as_ref().map(|elem| elem.expr.as_ref()) {
+                    Some(Expr::Lit(Lit::Str(Str { value, .. }))) => Ok(value.to_string()),
+                    _ => Err("`header.comments` must be an array of strings".to_string()),
+                })
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err("`header.comments` must be an array of strings".to_string()),
+            None => Vec::new(),
+        };
+        // `"pluralForms": false` leaves it out
+        let (plural_forms, no_plural_forms) = match object_prop(header, "pluralForms") {
+            Some(Expr::Lit(Lit::Bool(Bool { value: false, .. }))) => (None, true),
+            _ => (string("pluralForms")?, false),
+        };
+        let mut fields = Vec::new();
+        match object_prop(header, "fields") {
+            Some(Expr::Object(ObjectLit { props, .. })) => {
+                for prop in props {
+                    let PropOrSpread::Prop(prop) = prop else {
+                        continue;
+                    };
+                    let Prop::KeyValue(KeyValueProp {
+                        key: PropName::Str(Str { value: name, .. }),
+                        value,
+                    }) = prop.as_ref()
+                    else {
+                        continue;
+                    };
+                    let Expr::Lit(Lit::Str(Str { value, .. })) = value.as_ref() else {
+                        return Err(format!("`header.fields.{}` must be a string", name));
+                    };
+                    fields.push(HeaderField::new(name, value)?);
+                }
+            }
+            Some(_) => return Err("`header.fields` must be an object".to_string()),
+            None => {}
+        }
+
+        Ok(Self {
+            comments,
+            copyright_holder: string("copyrightHolder")?,
+            package_name: string("packageName")?,
+            package_version: string("packageVersion")?,
+            msgid_bugs_address: string("msgidBugsAddress")?,
+            pot_creation_date: string("potCreationDate")?,
+            plural_forms,
+            no_plural_forms,
+            fields,
+        })
+    }
+
+    /// Applies options from the command line over the ones from a config file
+    pub fn merge(self, overrides: Self) -> Self {
+        Self {
+            comments: match overrides.comments.is_empty() {
+                true => self.comments,
+                false => overrides.comments,
+            },
+            copyright_holder: overrides.copyright_holder.or(self.copyright_holder),
+            package_name: overrides.package_name.or(self.package_name),
+            package_version: overrides.package_version.or(self.package_version),
+            msgid_bugs_address: overrides.msgid_bugs_address.or(self.msgid_bugs_address),
+            pot_creation_date: overrides.pot_creation_date.or(self.pot_creation_date),
+            no_plural_forms: match overrides.plural_forms {
+                Some(_) => overrides.no_plural_forms,
+                None => overrides.no_plural_forms || self.no_plural_forms,
+            },
+            plural_forms: overrides.plural_forms.or(self.plural_forms),
+            fields: self.fields.into_iter().chain(overrides.fields).collect(),
+        }
+    }
+}
+
+/// A UTC date and time, written like xgettext writes `POT-Creation-Date`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+}
+impl From<SystemTime> for DateTime {
+    fn from(time: SystemTime) -> Self {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+        // See https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days.rem_euclid(146097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        Self {
+            year: year_of_era + era * 400 + i64::from(month <= 2),
+            month: month as u32,
+            day: day as u32,
+            hour: (seconds / 3600) as u32,
+            minute: (seconds % 3600 / 60) as u32,
+        }
+    }
+}
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}+0000",
+            self.year, self.month, self.day, self.hour, self.minute
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn writes_the_default_header() {
+        assert_eq!(
+            Header::new(&HeaderOptions::default(), UNIX_EPOCH).msgstr(),
+            "MIME-Version: 1.0\n\
+             Content-Type: text/plain; charset=utf-8\n\
+             Content-Transfer-Encoding: 8bit\n\
+             Plural-Forms: nplurals=2; plural=(n != 1);\n"
+        );
+    }
+
+    #[test]
+    fn builds_the_header_from_options() {
+        // 2024-02-29 13:05 UTC
+        let now = UNIX_EPOCH + Duration::from_secs(1709211900);
+        let header = Header::new(
+            &HeaderOptions {
+                comments: vec!["Our app".to_string()],
+                copyright_holder: Some("Acme Inc.".to_string()),
+                package_name: Some("app".to_string()),
+                package_version: Some("1.0".to_string()),
+                msgid_bugs_address: Some("i18n@example.com".to_string()),
+                pot_creation_date: Some("now".to_string()),
+                no_plural_forms: true,
+                fields: vec![
+                    "X-Generator=ours".parse().unwrap(),
+                    "Language-Team=French <fr@example.com>".parse().unwrap(),
+                    "X-Generator=theirs".parse().unwrap(),
+                ],
+                ..Default::default()
+            },
+            now,
+        );
+        assert_eq!(
+            header.comments,
+            vec![
+                "Our app",
+                "Copyright (C) 2024 Acme Inc.",
+                "This file is distributed under the same license as the app package.",
+            ]
+        );
+        assert_eq!(
+            header.fields(),
+            vec![
+                ("Project-Id-Version", "app 1.0"),
+                ("Report-Msgid-Bugs-To", "i18n@example.com"),
+                ("POT-Creation-Date", "2024-02-29 13:05+0000"),
+                ("Language-Team", "French <fr@example.com>"),
+                ("MIME-Version", "1.0"),
+                ("Content-Type", "text/plain; charset=utf-8"),
+                ("Content-Transfer-Encoding", "8bit"),
+                ("X-Generator", "theirs"),
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_dates() {
+        let date = |seconds| DateTime::from(UNIX_EPOCH + Duration::from_secs(seconds)).to_string();
+        assert_eq!(date(0), "1970-01-01 00:00+0000");
+        assert_eq!(date(951868799), "2000-02-29 23:59+0000");
+        assert_eq!(date(4102444800), "2100-01-01 00:00+0000");
+    }
+
+    #[test]
+    fn reads_the_creation_date_of_existing_files() {
+        assert_eq!(
+            creation_date(
+                r#"# Title
+msgid ""
+msgstr ""
+"Project-Id-Version: app\n"
+"POT-Creation-Date: "
+"2024-02-29 13:05+0000\n"
+
+msgid "POT-Creation-Date: never\n"
+msgstr ""
+"#
+            ),
+            Some("2024-02-29 13:05+0000".to_string())
+        );
+        assert_eq!(
+            creation_date("msgid \"\"\nmsgstr \"Content-Type: text/plain; charset=utf-8\\n\"\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_fields() {
+        assert_eq!(
+            "X-Generator = ours".parse(),
+            Ok(HeaderField {
+                name: "X-Generator".to_string(),
+                value: "ours".to_string()
+            })
+        );
+        assert!("Content-Type=text/html".parse::<HeaderField>().is_err());
+        assert!("Generator=ours".parse::<HeaderField>().is_err());
+        assert!("X-Generator".parse::<HeaderField>().is_err());
+    }
+
+    #[test]
+    fn parses_config_files() {
+        let config = HeaderOptions::parse(
+            r#"{
+                // Comments and trailing commas are allowed, like in tsconfig.json
+                "header": {
+                    "comments": ["Our app"],
+                    "packageName": "app",
+                    "pluralForms": false,
+                    "fields": { "X-Generator": "config", "Language-Team": "None" },
+                },
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            HeaderOptions {
+                comments: vec!["Our app".to_string()],
+                package_name: Some("app".to_string()),
+                no_plural_forms: true,
+                fields: vec![
+                    "X-Generator=config".parse().unwrap(),
+                    "Language-Team=None".parse().unwrap(),
+                ],
+                ..Default::default()
+            }
+        );
+        assert_eq!(HeaderOptions::parse("{}"), Ok(HeaderOptions::default()));
+        assert!(HeaderOptions::parse(r#"{ "header": { "packageName": 1 } }"#).is_err());
+        assert!(HeaderOptions::parse(r#"{ "header": { "fields": { "Foo": "x" } } }"#).is_err());
+
+        // Options from the command line win
+        let merged = config.merge(HeaderOptions {
+            package_name: Some("cli".to_string()),
+            plural_forms: Some("nplurals=1; plural=0;".to_string()),
+            fields: vec!["X-Generator=cli".parse().unwrap()],
+            ..Default::default()
+        });
+        assert_eq!(merged.package_name, Some("cli".to_string()));
+        assert_eq!(merged.comments, vec!["Our app".to_string()]);
+        assert!(!merged.no_plural_forms);
+        assert_eq!(
+            Header::new(&merged, UNIX_EPOCH).custom,
+            vec![("X-Generator".to_string(), "cli".to_string())]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet};
+
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_visit_type, Visit, VisitWith};
+
+/// How a keyword or component is referenced in code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalleeRef<'a> {
+    /// `name`
+    Ident(&'a str),
+    /// `object.name`, with the name of the object when it's a plain identifier
+    Member(Option<&'a str>, &'a str),
+}
+impl<'a> CalleeRef<'a> {
+    /// The name as written, ignoring the object
+    pub fn name(&self) -> &'a str {
+        match self {
+            CalleeRef::Ident(name) | CalleeRef::Member(_, name) => name,
+        }
+    }
+}
+
+/// Local names bound by imports from the modules keywords come from, e.g. `@our/i18n`
+#[derive(Debug, Default)]
+pub struct ImportBindings {
+    /// Local name to imported name, e.g. `t` to `gettext` for `import { gettext as t }`
+    functions: HashMap<String, String>,
+    /// Names bound to the whole module, e.g. `i18n` for `import * as i18n`,
+    /// `import i18n from` or `const i18n = require()`
+    namespaces: HashSet<String>,
+}
+impl ImportBindings {
+    /// Collects the bindings from ES imports and CommonJS `require` calls of the given modules
+    pub fn collect(module: &Module, sources: &[String]) -> Self {
+        let mut collector = ImportCollector {
+            sources,
+            bindings: Self::default(),
+        };
+        module.visit_with(&mut collector);
+        collector.bindings
+    }
+
+    /// Resolves a reference to its imported name.
+    /// Returns `None` when the reference isn't bound by an import from the configured modules.
+    pub fn resolve<'a>(&'a self, callee: CalleeRef<'a>) -> Option<&'a str> {
+        match callee {
+            CalleeRef::Ident(name) => self.functions.get(name).map(String::as_str),
+            CalleeRef::Member(Some(object), name) if self.namespaces.contains(object) => Some(name),
+            CalleeRef::Member(..) => None,
+        }
+    }
+}
+
+/// The name in `import { name }` or `export { name }`, which can also be a string like `import { "a-b" as ab }`
+pub fn module_export_name(name: &ModuleExportName) -> String {
+    match name {
+        ModuleExportName::Ident(Ident { sym, .. }) => sym.to_string(),
+        ModuleExportName::Str(Str { value, .. }) => value.to_string(),
+    }
+}
+
+struct ImportCollector<'a> {
+    sources: &'a [String],
+    bindings: ImportBindings,
+}
+impl ImportCollector<'_> {
+    fn is_source(&self, src: &str) -> bool {
+        self.sources.iter().any(|source| source == src)
+    }
+
+    /// Returns whether an expression is `require('source')` for a configured source
+    fn is_require(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Call(CallExpr {
+                callee: Callee::Expr(callee),
+                args,
+                ..
+            }) => match (callee.as_ref(), &args[..]) {
+                (Expr::Ident(Ident { sym, .. }), [ExprOrSpread { spread: None, expr }]) => {
+                    *sym == "require"
+                        && matches!(expr.as_ref(), Expr::Lit(Lit::Str(Str { value, .. })) if self.is_source(value))
+                }
+                _ => false,
+            },
+            Expr::Paren(ParenExpr { expr, .. }) => self.is_require(expr),
+            _ => false,
+        }
+    }
+}
+impl Visit for ImportCollector<'_> {
+    noop_visit_type!();
+
+    fn visit_import_decl(&mut self, n: &ImportDecl) {
+        if !self.is_source(&n.src.value) {
+            return;
+        }
+        for specifier in &n.specifiers {
+            match specifier {
+                ImportSpecifier::Named(ImportNamedSpecifier {
+                    local, imported, ..
+                }) => {
+                    let imported = imported
+                        .as_ref()
+                        .map(module_export_name)
+                        .unwrap_or_else(|| local.sym.to_string());
+                    self.bindings
+                        .functions
+                        .insert(local.sym.to_string(), imported);
+                }
+                ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+                | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => {
+                    self.bindings.namespaces.insert(local.sym.to_string());
+                }
+            }
+        }
+    }
+
+    fn visit_var_declarator(&mut self, n: &VarDeclarator) {
+        n.visit_children_with(self);
+
+        let Some(init) = &n.init else {
+            return;
+        };
+        match (&n.name, init.as_ref()) {
+            // const i18n = require('@our/i18n')
+            (Pat::Ident(BindingIdent { id, .. }), init) if self.is_require(init) => {
+                self.bindings.namespaces.insert(id.sym.to_string());
+            }
+            // const t = require('@our/i18n').gettext
+            (
+                Pat::Ident(BindingIdent { id, .. }),
+                Expr::Member(MemberExpr {
+                    obj,
+                    prop: MemberProp::Ident(Ident { sym, .. }),
+                    ..
+                }),
+            ) if self.is_require(obj) => {
+                self.bindings
+                    .functions
+                    .insert(id.sym.to_string(), sym.to_string());
+            }
+            // const { gettext: t, __ } = require('@our/i18n')
+            (Pat::Object(ObjectPat { props, .. }), init) if self.is_require(init) => {
+                for prop in props {
+                    match prop {
+                        ObjectPatProp::KeyValue(KeyValuePatProp {
+                            key: PropName::Ident(Ident { sym: imported, .. }),
+                            value,
+                        }) => {
+                            if let Pat::Ident(BindingIdent { id, .. }) = value.as_ref() {
+                                self.bindings
+                                    .functions
+                                    .insert(id.sym.to_string(), imported.to_string());
+                            }
+                        }
+                        ObjectPatProp::Assign(AssignPatProp { key, .. }) => {
+                            self.bindings
+                                .functions
+                                .insert(key.sym.to_string(), key.sym.to_string());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use itertools::Itertools;
+use swc_common::{errors::SourceMapper, BytePos, SourceMap, Span, Spanned};
+use swc_ecma_ast::*;
+
+use crate::{
+    eval::{eval_string, eval_string_with, EvalError, EvalErrorKind, Resolve},
+    imports::CalleeRef,
+};
+
+/// How a JSX element refers to its component, for both `<Trans>` and `<i18n.Trans>`
+pub fn element_ref(name: &JSXElementName) -> Option<CalleeRef<'_>> {
+    match name {
+        JSXElementName::Ident(Ident { sym, .. }) => Some(CalleeRef::Ident(sym)),
+        JSXElementName::JSXMemberExpr(JSXMemberExpr { obj, prop }) => Some(CalleeRef::Member(
+            match obj {
+                JSXObject::Ident(Ident { sym, .. }) => Some(sym),
+                JSXObject::JSXMemberExpr(_) => None,
+            },
+            &prop.sym,
+        )),
+        JSXElementName::JSXNamespacedName(_) => None,
+    }
+}
+
+/// Finds an attribute by name, e.g. `context` in `<Trans context="menu">`
+pub fn find_attribute<'a>(element: &'a JSXOpeningElement, name: &str) -> Option<&'a JSXAttr> {
+    element.attrs.iter().find_map(|attr| match attr {
+        JSXAttrOrSpread::JSXAttr(
+            attr @ JSXAttr {
+                name: JSXAttrName::Ident(Ident { sym, .. }),
+                ..
+            },
+        ) if *sym == name => Some(attr),
+        _ => None,
+    })
+}
+
+/// Evaluates an attribute to a string, for both `context="menu"` and `context={CONTEXT}`
+pub fn eval_attribute(attr: &JSXAttr, resolver: &dyn Resolve) -> Result<String, EvalError> {
+    match &attr.value {
+        Some(JSXAttrValue::Lit(Lit::Str(Str { value, .. }))) => Ok(value.to_string()),
+        Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+            expr: JSXExpr::Expr(expr),
+            ..
+        })) => eval_string_with(expr, resolver),
+        _ => Err(EvalError {
+            span: attr.span,
+            kind: EvalErrorKind::NonLiteral,
+        }),
+    }
+}
+
+/// Where the text of a translation component starts, skipping the whitespace after the opening tag
+pub fn children_span(cm: &SourceMap, children: &[JSXElementChild]) -> Option<Span> {
+    children.iter().find_map(|child| match child {
+        // The parsed value doesn't always match the source, e.g. it can have extra line breaks
+        JSXElementChild::JSXText(JSXText { span, .. }) => {
+            let source = cm.span_to_snippet(*span).ok()?;
+            let leading = source.len() - source.trim_start().len();
+            (leading < source.len()).then(|| span.with_lo(span.lo + BytePos(leading as u32)))
+        }
+        _ => Some(child.span()),
+    })
+}
+
+/// Serialises the children of a translation component into a msgid.
+///
+/// Nested elements become indexed tags (`Open <0>file</0>`), `{expressions}` become placeholders
+/// named after the variable or the last property (`Hello {name}` for `{user.name}`) or numbered
+/// for other expressions, and static strings like `{" "}` are kept as text. Also returns a
+/// description of each placeholder.
+pub fn serialize_children(
+    cm: &SourceMap,
+    children: &[JSXElementChild],
+) -> Result<(String, Vec<String>), EvalError> {
+    let mut serializer = Serializer {
+        cm,
+        msgid: String::new(),
+        placeholders: Vec::new(),
+        next_position: 0,
+        next_element: 0,
+    };
+    serializer.serialize(children)?;
+    let placeholders = serializer
+        .placeholders
+        .iter()
+        .map(|(placeholder, source)| format!("{} = {}", placeholder, source))
+        .collect();
+    Ok((serializer.msgid, placeholders))
+}
+
+struct Serializer<'a> {
+    cm: &'a SourceMap,
+    msgid: String,
+    /// Each placeholder and the source of its expression
+    placeholders: Vec<(String, String)>,
+    /// The number of the next placeholder that has no name
+    next_position: usize,
+    next_element: usize,
+}
+impl Serializer<'_> {
+    fn serialize(&mut self, children: &[JSXElementChild]) -> Result<(), EvalError> {
+        for child in children {
+            match child {
+                JSXElementChild::JSXText(JSXText { value, .. }) => {
+                    self.msgid.push_str(&clean_jsx_text(value))
+                }
+                JSXElementChild::JSXExprContainer(JSXExprContainer {
+                    expr: JSXExpr::JSXEmptyExpr(_),
+                    ..
+                }) => {}
+                JSXElementChild::JSXExprContainer(JSXExprContainer {
+                    expr: JSXExpr::Expr(expr),
+                    ..
+                }) => match eval_string(expr) {
+                    Ok(value) => self.msgid.push_str(&value),
+                    Err(_) => {
+                        let placeholder = self.placeholder(expr);
+                        self.msgid.push_str(&placeholder);
+                    }
+                },
+                JSXElementChild::JSXElement(element) => {
+                    let index = self.next_element;
+                    self.next_element += 1;
+                    if element.children.is_empty() {
+                        self.msgid.push_str(&format!("<{}/>", index));
+                    } else {
+                        self.msgid.push_str(&format!("<{}>", index));
+                        self.serialize(&element.children)?;
+                        self.msgid.push_str(&format!("</{}>", index));
+                    }
+                }
+                JSXElementChild::JSXFragment(fragment) => self.serialize(&fragment.children)?,
+                JSXElementChild::JSXSpreadChild(spread) => {
+                    return Err(EvalError {
+                        span: spread.span,
+                        kind: EvalErrorKind::NonLiteral,
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The placeholder of an expression, the same one each time the expression is repeated
+    fn placeholder(&mut self, expr: &Expr) -> String {
+        let source = self
+            .cm
+            .span_to_snippet(expr.span())
+            .map(|source| source.split_whitespace().join(" "))
+            .unwrap_or_default();
+        if let Some((placeholder, _)) = self.placeholders.iter().find(|(_, s)| *s == source) {
+            return placeholder.clone();
+        }
+
+        let name = match expr {
+            Expr::Ident(Ident { sym, .. }) => Some(sym),
+            Expr::Member(MemberExpr {
+                prop: MemberProp::Ident(Ident { sym, .. }),
+                ..
+            }) => Some(sym),
+            _ => None,
+        };
+        // Different expressions with the same name, like `{user.name}` and `{name}`, are numbered
+        let placeholder = match name.map(|name| format!("{{{}}}", name)) {
+            Some(named) if !self.placeholders.iter().any(|(p, _)| *p == named) => named,
+            _ => {
+                self.next_position += 1;
+                format!("{{{}}}", self.next_position - 1)
+            }
+        };
+        self.placeholders.push((placeholder.clone(), source));
+        placeholder
+    }
+}
+
+/// Applies JSX whitespace rules to a text child: lines are trimmed, empty lines are removed
+/// and the remaining lines are joined with a space
+fn clean_jsx_text(text: &str) -> String {
+    let lines = text
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect::<Vec<_>>();
+    let last_non_empty = lines
+        .iter()
+        .rposition(|line| line.chars().any(|c| c != ' ' && c != '\t'))
+        .unwrap_or(0);
+
+    let mut result = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        let mut line = line.replace('\t', " ");
+        if index != 0 {
+            line = line.trim_start_matches(' ').to_string();
+        }
+        if index != lines.len() - 1 {
+            line = line.trim_end_matches(' ').to_string();
+        }
+        if !line.is_empty() {
+            result.push_str(&line);
+            if index != last_non_empty {
+                result.push(' ');
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn cleans_jsx_text_like_react() {
+        assert_eq!(clean_jsx_text("Open "), "Open ");
+        assert_eq!(clean_jsx_text("Open \n"), "Open");
+        assert_eq!(
+            clean_jsx_text("\n    Open\n    the file\n  "),
+            "Open the file"
+        );
+        assert_eq!(clean_jsx_text("  a  \n\n  b"), "  a b");
+        assert_eq!(clean_jsx_text("\n  \n"), "");
+        assert_eq!(clean_jsx_text(" "), " ");
+    }
+}
@@ -0,0 +1,325 @@
+// See https://www.gnu.org/software/gettext/manual/html_node/xgettext-Invocation.html for the
+// `--keyword` format this module is modelled after
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+/// Keywords recognised when `--no-default-keywords` isn't used
+pub const DEFAULT_KEYWORDS: &[&str] = &[
+    "__",
+    "gettext",
+    "__n:1,2",
+    "ngettext:1,2",
+    "__p:1c,2",
+    "pgettext:1c,2",
+    "__np:1c,2,3",
+    "npgettext:1c,2,3",
+    "__d:1d,2",
+    "dgettext:1d,2",
+    "__dn:1d,2,3",
+    "dngettext:1d,2,3",
+    "__dp:1d,2c,3",
+    "dpgettext:1d,2c,3",
+    "__dnp:1d,2c,3,4",
+    "dnpgettext:1d,2c,3,4",
+];
+
+/// No-op markers recognised when `--no-default-keywords` isn't used. Their messages are extracted
+/// like other keywords, but translated later, e.g. `const LABELS = { open: N_('Open') }` then `__(label)`.
+pub const DEFAULT_NOOP_KEYWORDS: &[&str] = &[
+    "N_",
+    "gettext_noop",
+    "dgettext_noop:1d,2",
+    "pgettext_noop:1c,2",
+    "dpgettext_noop:1d,2c,3",
+    "ngettext_noop:1,2",
+    "dngettext_noop:1d,2,3",
+];
+
+/// A function name and the positions of the arguments that make up a message.
+///
+/// Positions are zero-based, while the textual spec (e.g. `tp:1c,2`) is one-based like xgettext.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyword {
+    pub name: String,
+    pub domain: Option<usize>,
+    pub context: Option<usize>,
+    pub msgid: usize,
+    pub plural: Option<usize>,
+    /// Whether this is a no-op marker, whose messages are translated later
+    pub deferred: bool,
+}
+
+impl Keyword {
+    /// Turns the keyword into a no-op marker
+    pub fn into_deferred(self) -> Self {
+        Self {
+            deferred: true,
+            ..self
+        }
+    }
+
+    /// The minimum number of arguments a call needs for the message to be extracted
+    pub fn arity(&self) -> usize {
+        [self.domain, self.context, Some(self.msgid), self.plural]
+            .into_iter()
+            .flatten()
+            .max()
+            .unwrap_or(0)
+            + 1
+    }
+}
+
+impl FromStr for Keyword {
+    type Err = String;
+
+    /// Parses `name`, `name:1`, `name:1,2`, `name:1c,2`, `name:1d,2c,3,4` and so on.
+    /// Plain numbers are the msgid then the plural, `c` marks the context and `d` the domain.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let (name, positions) = match spec.split_once(':') {
+            Some((name, positions)) => (name.trim(), Some(positions)),
+            None => (spec.trim(), None),
+        };
+        if name.is_empty() {
+            return Err(format!("Missing function name in keyword `{}`", spec));
+        }
+
+        let mut keyword = Keyword {
+            name: name.to_string(),
+            domain: None,
+            context: None,
+            msgid: 0,
+            plural: None,
+            deferred: false,
+        };
+        let Some(positions) = positions else {
+            return Ok(keyword);
+        };
+
+        let mut msgid = None;
+        let mut seen = Vec::<usize>::new();
+        for part in positions.split(',').map(str::trim) {
+            let (digits, kind) = match part.strip_suffix('c') {
+                Some(digits) => (digits, Some('c')),
+                None => match part.strip_suffix('d') {
+                    Some(digits) => (digits, Some('d')),
+                    None => (part, None),
+                },
+            };
+            let position = match digits.parse::<usize>() {
+                Ok(position) if position > 0 => position - 1,
+                _ => return Err(format!("Invalid argument `{}` in keyword `{}`", part, spec)),
+            };
+            if seen.contains(&position) {
+                return Err(format!(
+                    "Argument {} is used twice in keyword `{}`",
+                    position + 1,
+                    spec
+                ));
+            }
+            seen.push(position);
+
+            let slot = match kind {
+                Some('c') => &mut keyword.context,
+                Some('d') => &mut keyword.domain,
+                _ if msgid.is_none() => &mut msgid,
+                _ => &mut keyword.plural,
+            };
+            if slot.is_some() {
+                return Err(format!("Too many arguments in keyword `{}`", spec));
+            }
+            *slot = Some(position);
+        }
+
+        match msgid {
+            Some(msgid) => {
+                keyword.msgid = msgid;
+                Ok(keyword)
+            }
+            None => Err(format!("Missing msgid argument in keyword `{}`", spec)),
+        }
+    }
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut positions = vec![(self.msgid, "")];
+        if let Some(plural) = self.plural {
+            positions.push((plural, ""));
+        }
+        if let Some(context) = self.context {
+            positions.push((context, "c"));
+        }
+        if let Some(domain) = self.domain {
+            positions.push((domain, "d"));
+        }
+        positions.sort();
+
+        write!(f, "{}:", self.name)?;
+        for (index, (position, suffix)) in positions.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}{}", position + 1, suffix)?;
+        }
+        Ok(())
+    }
+}
+
+/// The set of keywords used during extraction, indexed by function name
+#[derive(Debug, Clone)]
+pub struct Keywords {
+    keywords: HashMap<String, Keyword>,
+}
+impl Keywords {
+    /// Creates a keyword set from the defaults (if enabled) and the given extra keywords.
+    /// Extra keywords replace defaults with the same name.
+    pub fn new(use_defaults: bool, extra: impl IntoIterator<Item = Keyword>) -> Self {
+        let mut keywords = HashMap::new();
+        if use_defaults {
+            for spec in DEFAULT_KEYWORDS {
+                let keyword: Keyword = spec.parse().expect("Invalid default keyword");
+                keywords.insert(keyword.name.clone(), keyword);
+            }
+            for spec in DEFAULT_NOOP_KEYWORDS {
+                let keyword = spec
+                    .parse::<Keyword>()
+                    .expect("Invalid default keyword")
+                    .into_deferred();
+                keywords.insert(keyword.name.clone(), keyword);
+            }
+        }
+        for keyword in extra {
+            keywords.insert(keyword.name.clone(), keyword);
+        }
+        Self { keywords }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Keyword> {
+        self.keywords.get(name)
+    }
+}
+impl Default for Keywords {
+    fn default() -> Self {
+        Self::new(true, [])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn parses_keyword_specs() {
+        assert_eq!(
+            "t".parse::<Keyword>().unwrap(),
+            Keyword {
+                name: "t".to_string(),
+                domain: None,
+                context: None,
+                msgid: 0,
+                plural: None,
+                deferred: false,
+            }
+        );
+        assert_eq!(
+            "tn:1,2".parse::<Keyword>().unwrap(),
+            Keyword {
+                name: "tn".to_string(),
+                domain: None,
+                context: None,
+                msgid: 0,
+                plural: Some(1),
+                deferred: false,
+            }
+        );
+        assert_eq!(
+            "translate:2,1c".parse::<Keyword>().unwrap(),
+            Keyword {
+                name: "translate".to_string(),
+                domain: None,
+                context: Some(0),
+                msgid: 1,
+                plural: None,
+                deferred: false,
+            }
+        );
+        assert_eq!(
+            "tdn:1d,2,3".parse::<Keyword>().unwrap(),
+            Keyword {
+                name: "tdn".to_string(),
+                domain: Some(0),
+                context: None,
+                msgid: 1,
+                plural: Some(2),
+                deferred: false,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_keyword_specs() {
+        assert!(":1".parse::<Keyword>().is_err());
+        assert!("t:0".parse::<Keyword>().is_err());
+        assert!("t:x".parse::<Keyword>().is_err());
+        assert!("t:1c".parse::<Keyword>().is_err());
+        assert!("t:1,1c".parse::<Keyword>().is_err());
+        assert!("t:1,2,3".parse::<Keyword>().is_err());
+        assert!("t:1c,2c,3".parse::<Keyword>().is_err());
+    }
+
+    #[test]
+    fn displays_keywords_as_specs() {
+        for spec in DEFAULT_KEYWORDS
+            .iter()
+            .chain(DEFAULT_NOOP_KEYWORDS)
+            .filter(|spec| spec.contains(':'))
+        {
+            assert_eq!(&spec.parse::<Keyword>().unwrap().to_string(), spec);
+        }
+        assert_eq!("__".parse::<Keyword>().unwrap().to_string(), "__:1");
+    }
+
+    #[test]
+    fn computes_arity() {
+        assert_eq!("t".parse::<Keyword>().unwrap().arity(), 1);
+        assert_eq!("t:2".parse::<Keyword>().unwrap().arity(), 2);
+        assert_eq!("tn:1,2".parse::<Keyword>().unwrap().arity(), 2);
+        assert_eq!("tdnp:1d,2c,3,4".parse::<Keyword>().unwrap().arity(), 4);
+        assert_eq!("tp:3c,1".parse::<Keyword>().unwrap().arity(), 3);
+    }
+
+    #[test]
+    fn extra_keywords_extend_or_replace_defaults() {
+        let keywords = Keywords::new(true, ["t:1".parse().unwrap(), "__:2".parse().unwrap()]);
+        assert_eq!(keywords.get("t").unwrap().msgid, 0);
+        assert_eq!(keywords.get("__").unwrap().msgid, 1);
+        assert!(keywords.get("gettext").is_some());
+
+        let keywords = Keywords::new(false, ["t:1".parse().unwrap()]);
+        assert!(keywords.get("t").is_some());
+        assert!(keywords.get("__").is_none());
+        assert!(keywords.get("N_").is_none());
+    }
+
+    #[test]
+    fn includes_noop_markers() {
+        let keywords = Keywords::default();
+        assert!(keywords.get("N_").unwrap().deferred);
+        assert!(keywords.get("dpgettext_noop").unwrap().deferred);
+        assert!(!keywords.get("gettext").unwrap().deferred);
+
+        // Markers can be configured like other keywords, and replace keywords with the same name
+        let keywords = Keywords::new(
+            true,
+            [
+                "markLater:1c,2".parse::<Keyword>().unwrap().into_deferred(),
+                "__".parse::<Keyword>().unwrap().into_deferred(),
+            ],
+        );
+        assert_eq!(keywords.get("markLater").unwrap().context, Some(0));
+        assert!(keywords.get("markLater").unwrap().deferred);
+        assert!(keywords.get("__").unwrap().deferred);
+    }
+}
@@ -2,11 +2,25 @@ use clap::Parser;
 use std::{
     fs,
     io::Write,
+    process::ExitCode,
     sync::{Arc, Mutex},
     time::Duration,
 };
 
+mod check;
+mod constants;
+mod diagnostics;
+mod directives;
+mod eval;
+mod exports;
+mod flags;
+mod header;
+mod imports;
+mod jsx;
+mod keywords;
+mod po;
 mod pot;
+mod suppressions;
 mod visitor;
 mod walker;
 
@@ -37,52 +51,251 @@ struct Cli {
     /// The default domain to use for strings that don't have a domain specified.
     #[arg(long, default_value = "default")]
     default_domain: String,
+    /// An additional keyword to look for, in xgettext format. E.g. `t`, `tn:1,2`, `tp:1c,2` or `tdn:1d,2,3`.
+    /// Plain numbers are the msgid and plural arguments, `c` marks the context and `d` the domain.
+    #[arg(long, value_name = "SPEC")]
+    keyword: Vec<keywords::Keyword>,
+    /// A no-op marker to look for, in the same format as `--keyword`. Its messages are extracted but translated later,
+    /// e.g. `markLater` or `markLater:1c,2`.
+    #[arg(long, value_name = "SPEC")]
+    noop_keyword: Vec<keywords::Keyword>,
+    /// Don't look for the built-in keywords (`__`, `gettext`, `__n`, `ngettext`, ...) and no-op markers (`N_`, `gettext_noop`, ...),
+    /// only those passed with `--keyword` and `--noop-keyword`
+    #[arg(long)]
+    no_default_keywords: bool,
+    /// Report gettext calls that can't be extracted as errors, and exit with a non-zero code if there are any
+    #[arg(long)]
+    warnings_as_errors: bool,
+    /// How `${}` expressions in tagged templates like `` __`Hello ${name}` `` are written in the msgid
+    #[arg(long, value_enum, default_value_t)]
+    placeholders: visitor::PlaceholderStyle,
+    /// Also extract comments on the lines before a gettext call, when they start with TAG (e.g. `TRANSLATORS:`).
+    /// Without a TAG, all comments before the call are extracted.
+    #[arg(long, value_name = "TAG", num_args = 0..=1, require_equals = true, default_missing_value = "")]
+    add_comments: Option<String>,
+    /// A JSX component to extract messages from, e.g. `Trans`. The msgid comes from the `msgid` attribute or the children,
+    /// and the `context`, `domain`, `plural` and `comment` attributes are extracted too.
+    #[arg(long, value_name = "NAME")]
+    jsx_component: Vec<String>,
+    /// Only count keywords and JSX components that are imported from this module, e.g. `@our/i18n`.
+    /// Renamed imports, namespace imports and CommonJS `require` calls are supported.
+    #[arg(long, value_name = "MODULE")]
+    import_source: Vec<String>,
+    /// Resolve constants imported from other project files in gettext arguments, like `CONTEXT` in `__p(CONTEXT, 'File')`.
+    /// Exported string constants are indexed before extracting, and imports that can't be found are reported.
+    #[arg(long)]
+    resolve_imports: bool,
+    /// The tsconfig.json whose `baseUrl` and `paths` are used by `--resolve-imports`.
+    /// Defaults to the tsconfig.json in the path, if there is one.
+    #[arg(long, value_name = "FILE")]
+    tsconfig: Option<std::path::PathBuf>,
+    /// Which parts of the source location are written in references, like xgettext
+    #[arg(long, value_enum, default_value_t)]
+    add_location: visitor::AddLocation,
+    /// Also write the column in references, e.g. `src/app.ts:12:5`, for editors that can open a location
+    #[arg(long)]
+    location_columns: bool,
+    /// Which line references point to when a call spans several lines
+    #[arg(long, value_enum, default_value_t)]
+    reference_line: visitor::ReferenceLine,
+    /// Write line breaks in messages as spaces, like earlier versions did.
+    /// The msgids of these messages then don't match the strings the code looks up.
+    #[arg(long)]
+    collapse_newlines: bool,
+    /// The number of columns long lines are wrapped at, measured like a terminal displays them.
+    /// Like msgcat, widths below 20 are raised to 20.
+    #[arg(long, value_name = "N", default_value_t = pot::DEFAULT_WIDTH)]
+    width: usize,
+    /// Don't wrap long messages and comments. Messages are still broken after line breaks.
+    #[arg(long)]
+    no_wrap: bool,
+    /// The order messages are written in
+    #[arg(long, value_enum, default_value_t)]
+    sort_output: pot::SortOutput,
+    /// A JSON file whose `header` object sets the header options below, e.g. `{ "header": { "packageName": "app" } }`.
+    /// Options passed on the command line win over the file.
+    #[arg(long, value_name = "FILE")]
+    header_config: Option<std::path::PathBuf>,
+    /// A comment line written before the header, like a title. Can be repeated.
+    #[arg(long, value_name = "TEXT")]
+    header_comment: Vec<String>,
+    /// Adds a copyright notice for HOLDER to the header comment
+    #[arg(long, value_name = "HOLDER")]
+    copyright_holder: Option<String>,
+    /// The package name written in `Project-Id-Version` and in the header comment
+    #[arg(long, value_name = "NAME")]
+    package_name: Option<String>,
+    /// The package version written in `Project-Id-Version`
+    #[arg(long, value_name = "VERSION")]
+    package_version: Option<String>,
+    /// Where to report bugs in msgids, written in `Report-Msgid-Bugs-To`
+    #[arg(long, value_name = "ADDRESS")]
+    msgid_bugs_address: Option<String>,
+    /// Write `POT-Creation-Date`, with the time of extraction or the given DATE
+    #[arg(long, value_name = "DATE", num_args = 0..=1, require_equals = true, default_missing_value = "now")]
+    pot_creation_date: Option<String>,
+    /// The `Plural-Forms` of the source language. Defaults to English, `nplurals=2; plural=(n != 1);`
+    #[arg(long, value_name = "FORMS")]
+    plural_forms: Option<String>,
+    /// Don't write `Plural-Forms`, leaving it to translators
+    #[arg(long, conflicts_with = "plural_forms")]
+    no_plural_forms: bool,
+    /// A header field, e.g. `Language-Team=French <fr@example.com>` or `X-Generator=our-tool`.
+    /// Any standard field can be set, and custom fields start with `X-`. Can be repeated.
+    #[arg(long = "header", value_name = "NAME=VALUE")]
+    header_fields: Vec<header::HeaderField>,
+    /// Don't write anything, but compare the extracted messages with the POT files in the output folder, e.g. in CI.
+    /// Prints a diff and exits with a non-zero code if any file is out of date, or is for a domain without messages.
+    #[arg(long)]
+    check: bool,
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Cli::parse();
     run(args)
 }
 
 use indicatif::ProgressBar;
 
-fn run(args: Cli) {
+fn run(args: Cli) -> ExitCode {
     let default_domain = args.default_domain;
     let exclude = args.exclude;
     let path = args.path.unwrap_or(std::path::PathBuf::from("."));
     let output_folder = args.output_folder;
     let references_relative_to = args.references_relative_to.unwrap_or(output_folder.clone());
+    let mut options = visitor::ExtractOptions {
+        keywords: keywords::Keywords::new(
+            !args.no_default_keywords,
+            args.keyword.into_iter().chain(
+                args.noop_keyword
+                    .into_iter()
+                    .map(keywords::Keyword::into_deferred),
+            ),
+        ),
+        placeholders: args.placeholders,
+        add_comments: args.add_comments,
+        jsx_components: args.jsx_component,
+        import_sources: args.import_source,
+        exports: None,
+        add_location: args.add_location,
+        location_columns: args.location_columns,
+        reference_line: args.reference_line,
+    };
+
+    let format_options = pot::FormatOptions {
+        collapse_newlines: args.collapse_newlines,
+        width: args.width.max(pot::MIN_WIDTH),
+        wrap: !args.no_wrap,
+        sort: args.sort_output,
+    };
+    let header_options = header::HeaderOptions {
+        comments: args.header_comment,
+        copyright_holder: args.copyright_holder,
+        package_name: args.package_name,
+        package_version: args.package_version,
+        msgid_bugs_address: args.msgid_bugs_address,
+        pot_creation_date: args.pot_creation_date,
+        plural_forms: args.plural_forms,
+        no_plural_forms: args.no_plural_forms,
+        fields: args.header_fields,
+    };
+    let header_options = match args.header_config {
+        Some(path) => match header::HeaderOptions::load(&path) {
+            Ok(config) => config.merge(header_options),
+            Err(e) => {
+                panic!("Error reading header config: {}", e);
+            }
+        },
+        None => header_options,
+    };
+    let header = header::Header::new(&header_options, std::time::SystemTime::now());
+    let warnings_as_errors = args.warnings_as_errors;
 
     let pot = Arc::new(Mutex::new(pot::POT::new(default_domain)));
+    let diagnostics = Arc::new(Mutex::new(diagnostics::Diagnostics::new()));
+
+    let files = match walker::find_ts_files(path.clone(), exclude) {
+        Ok(entries) => entries.map(|entry| entry.into_path()).collect::<Vec<_>>(),
+        Err(e) => {
+            panic!("Error reading path: {}", e);
+        }
+    };
+
+    if args.resolve_imports {
+        let bar = ProgressBar::new_spinner();
+        bar.enable_steady_tick(Duration::from_millis(100));
+        bar.set_message("Indexing exported constants...");
+
+        let tsconfig = args
+            .tsconfig
+            .or_else(|| Some(path.join("tsconfig.json")).filter(|path| path.is_file()))
+            .map(|tsconfig| match exports::TsConfigPaths::load(&tsconfig) {
+                Ok(tsconfig) => tsconfig,
+                Err(e) => {
+                    panic!("Error reading tsconfig: {}", e);
+                }
+            });
+        options.exports = Some(exports::ExportIndex::build(&files, tsconfig));
+
+        bar.finish_with_message("Done indexing exported constants");
+    }
 
     {
         let bar = ProgressBar::new_spinner();
         bar.enable_steady_tick(Duration::from_millis(100));
         bar.set_message("Reading files...");
 
-        match walker::find_ts_files(path, exclude) {
-            Ok(entries) => {
-                for entry in entries {
-                    bar.set_message(format!(
-                        "Reading {}",
-                        entry.path().to_str().unwrap_or("unknown")
-                    ));
-                    bar.inc(1);
-
-                    walker::parse_file(
-                        &entry.into_path(),
-                        Arc::clone(&pot),
-                        &references_relative_to,
-                    );
-                }
-            }
-            Err(e) => {
-                panic!("Error reading path: {}", e);
-            }
+        for (file_order, file) in files.iter().enumerate() {
+            bar.set_message(format!("Reading {}", file.to_str().unwrap_or("unknown")));
+            bar.inc(1);
+
+            walker::parse_file(
+                file,
+                file_order,
+                Arc::clone(&pot),
+                &references_relative_to,
+                &options,
+                Arc::clone(&diagnostics),
+                warnings_as_errors,
+            );
         }
         bar.finish_with_message("Done reading source files");
     };
-    {
+    let up_to_date = if args.check {
+        let domains = &pot.lock().unwrap().domains;
+
+        let mut out_of_date = Vec::new();
+        for (domain, pot_file) in domains {
+            let file_path = output_folder.join(format!("{}.pot", domain));
+            let existing = fs::read_to_string(&file_path).ok();
+            let extracted = match &existing {
+                Some(existing) => {
+                    pot_file.convert_to_string_like(existing, &header, &format_options)
+                }
+                None => pot_file.convert_to_string(&header, &format_options),
+            };
+            if let Some(diff) = check::diff(&file_path, existing.as_deref(), &extracted) {
+                print!("{}", diff);
+                out_of_date.push(domain.as_str());
+            }
+        }
+        let stale = check::stale_files(&output_folder, |domain| domains.contains_key(domain));
+        for file in &stale {
+            eprintln!(
+                "{} is for a domain that no longer has messages",
+                file.to_str().unwrap_or("unknown")
+            );
+        }
+
+        if !out_of_date.is_empty() {
+            eprintln!(
+                "{} POT file(s) are out of date: {}",
+                out_of_date.len(),
+                out_of_date.join(", ")
+            );
+        }
+        out_of_date.is_empty() && stale.is_empty()
+    } else {
         let bar = ProgressBar::new_spinner();
         bar.enable_steady_tick(Duration::from_millis(100));
 
@@ -101,8 +314,16 @@ fn run(args: Cli) {
 
         bar.set_length(domains.len() as u64);
 
+        let mut changed = Vec::new();
         domains.iter().for_each(|(domain, pot_file)| {
             let file_path = output_folder.join(format!("{}.pot", domain));
+            bar.inc(1);
+            // Files that only differ by their creation date are left as they are
+            if fs::read_to_string(&file_path)
+                .is_ok_and(|existing| pot_file.is_unchanged(&existing, &header, &format_options))
+            {
+                return;
+            }
             bar.set_message(format!(
                 "Writing {}",
                 file_path.to_str().unwrap_or("unknown")
@@ -113,9 +334,13 @@ fn run(args: Cli) {
                     panic!("Failed to create file: {}", e);
                 }
             };
-            match file.write_all(pot_file.convert_to_string().as_bytes()) {
+            match file.write_all(
+                pot_file
+                    .convert_to_string(&header, &format_options)
+                    .as_bytes(),
+            ) {
                 Ok(_) => {
-                    bar.inc(1);
+                    changed.push(domain.as_str());
                 }
                 Err(e) => {
                     panic!("Failed to write file: {}", e);
@@ -124,7 +349,48 @@ fn run(args: Cli) {
         });
 
         bar.finish_with_message("Done writing POT files");
+        if changed.is_empty() {
+            eprintln!("No POT files changed");
+        } else {
+            eprintln!(
+                "{} POT file(s) changed: {}",
+                changed.len(),
+                changed.join(", ")
+            );
+        }
+        true
     };
+
+    let diagnostics = diagnostics.lock().unwrap();
+    if diagnostics.unparseable_files() > 0 {
+        eprintln!(
+            "{} file(s) could not be read or parsed and were skipped",
+            diagnostics.unparseable_files()
+        );
+    }
+    if diagnostics.suppressed() > 0 {
+        eprintln!(
+            "{} gettext call(s) skipped by gettext-extractor-disable comments",
+            diagnostics.suppressed()
+        );
+    }
+    if !diagnostics.is_empty() {
+        let skipped = diagnostics.skipped_calls();
+        if skipped > 0 {
+            eprintln!("{} gettext call(s) could not be extracted", skipped);
+        }
+        if diagnostics.len() > skipped {
+            eprintln!(
+                "{} i18n comment directive(s) were ignored",
+                diagnostics.len() - skipped
+            );
+        }
+    }
+    if !up_to_date || (warnings_as_errors && !diagnostics.is_empty()) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
 }
 
 #[cfg(test)]
@@ -140,6 +406,131 @@ mod tests {
         Cli::command().debug_assert();
     }
 
+    #[test]
+    fn fails_with_warnings_as_errors() {
+        let output_folder = std::env::temp_dir().join("ts-gettext-extractor-warnings-as-errors");
+        let args = [
+            "",
+            "--path",
+            "./tests/invalid/",
+            "--output-folder",
+            output_folder.to_str().unwrap(),
+        ];
+        assert_eq!(run(Cli::parse_from(args)), ExitCode::SUCCESS);
+        assert_eq!(
+            run(Cli::parse_from(
+                args.iter().chain(&["--warnings-as-errors"])
+            )),
+            ExitCode::FAILURE
+        );
+        // Strings that could be extracted are still written, even when other calls are malformed
+        assert!(fs::read_to_string(output_folder.join("default.pot"))
+            .unwrap()
+            .contains("msgid \"Extracted\""));
+    }
+
+    #[test]
+    fn skips_unparseable_files() {
+        let output_folder = std::env::temp_dir().join("ts-gettext-extractor-unparseable");
+        let args = [
+            "",
+            "--path",
+            "./tests/unparseable/",
+            "--output-folder",
+            output_folder.to_str().unwrap(),
+        ];
+        assert_eq!(run(Cli::parse_from(args)), ExitCode::SUCCESS);
+        let pot = fs::read_to_string(output_folder.join("default.pot")).unwrap();
+        assert!(pot.contains("msgid \"Extracted\""));
+        assert!(!pot.contains("msgid \"Unparseable\""));
+    }
+
+    #[test]
+    fn resolves_imported_constants() {
+        let output_folder = std::env::temp_dir().join("ts-gettext-extractor-resolve-imports");
+        let _ = fs::remove_dir_all(&output_folder);
+        let args = [
+            "",
+            "--path",
+            "./tests/imports/",
+            "--output-folder",
+            output_folder.to_str().unwrap(),
+            "--references-relative-to",
+            "./tests/imports/",
+            "--resolve-imports",
+            "--warnings-as-errors",
+        ];
+        // `./missing` can't be resolved
+        assert_eq!(run(Cli::parse_from(args)), ExitCode::FAILURE);
+        assert_eq!(
+            fs::read_to_string(output_folder.join("billing.pot")).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: src/app.ts:6
+msgctxt "checkout"
+msgid "Pay now"
+msgstr ""
+"#
+        );
+        assert_eq!(
+            fs::read_to_string(output_folder.join("default.pot")).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: src/app.ts:8
+msgctxt "checkout"
+msgid "Save"
+msgstr ""
+
+#: src/app.ts:7
+msgctxt "toolbar"
+msgid "Save"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn checks_pot_files_are_up_to_date() {
+        let output_folder = std::env::temp_dir().join("ts-gettext-extractor-check");
+        let _ = fs::remove_dir_all(&output_folder);
+        let args = [
+            "",
+            "--path",
+            "./tests/imports/",
+            "--output-folder",
+            output_folder.to_str().unwrap(),
+            "--resolve-imports",
+            "--pot-creation-date",
+        ];
+        let check = || run(Cli::parse_from(args.iter().chain(&["--check"])));
+        // Nothing is written when checking
+        assert_eq!(check(), ExitCode::FAILURE);
+        assert!(!output_folder.exists());
+
+        assert_eq!(run(Cli::parse_from(args)), ExitCode::SUCCESS);
+        assert_eq!(check(), ExitCode::SUCCESS);
+
+        let default_pot = output_folder.join("default.pot");
+        let content = fs::read_to_string(&default_pot).unwrap();
+        fs::write(&default_pot, content.replace("\"Save\"", "\"Store\"")).unwrap();
+        assert_eq!(check(), ExitCode::FAILURE);
+        fs::write(&default_pot, content).unwrap();
+        assert_eq!(check(), ExitCode::SUCCESS);
+
+        fs::write(output_folder.join("removed.pot"), "").unwrap();
+        assert_eq!(check(), ExitCode::FAILURE);
+    }
+
     #[test]
     fn verify_snapshot() {
         let _ = fs::remove_dir_all("./tests/output/");
@@ -154,7 +545,7 @@ mod tests {
             "--references-relative-to",
             "./tests",
         ]);
-        run(args);
+        assert_eq!(run(args), ExitCode::SUCCESS);
         for entry in WalkDir::new("./tests/output/")
             .into_iter()
             .filter_map(|entry| entry.ok())
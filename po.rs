@@ -0,0 +1,800 @@
+// See https://www.gnu.org/software/gettext/manual/html_node/PO-Files.html for details about the
+// PO file format
+
+use std::{collections::BTreeSet, fmt};
+
+use itertools::Itertools;
+
+use crate::pot::{
+    format_po_comment, format_po_flags, format_po_message, format_po_references, FormatOptions,
+    Reference,
+};
+
+/// A PO or POT file, with its entries in the order they're written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Catalog {
+    pub entries: Vec<Entry>,
+}
+impl Catalog {
+    /// The header entry, the message with an empty msgid and no context
+    pub fn header(&self) -> Option<&Entry> {
+        self.entries
+            .iter()
+            .find(|entry| !entry.obsolete && entry.msgctxt.is_none() && entry.msgid.is_empty())
+    }
+
+    /// The value of a header field, like `POT-Creation-Date`
+    pub fn header_field(&self, name: &str) -> Option<&str> {
+        self.header()?
+            .msgstr
+            .first()?
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(field, _)| field.trim() == name)
+            .map(|(_, value)| value.trim())
+    }
+
+    /// Writes the catalog like xgettext and msgcat. POT files are written with it too, so that a
+    /// POT file read back is written the same.
+    pub fn convert_to_string(&self, options: &FormatOptions) -> String {
+        self.entries
+            .iter()
+            .map(|entry| entry.convert_to_string(options))
+            .join("\n")
+    }
+}
+
+/// A message of a PO file, with its translations and comments
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entry {
+    /// `# ` comments, written by translators
+    pub translator_comments: Vec<String>,
+    /// `#.` comments, extracted from the source
+    pub extracted_comments: Vec<String>,
+    /// `#:` references
+    pub references: BTreeSet<Reference>,
+    /// `#,` flags, like `fuzzy` or `c-format`, in the order they're written
+    pub flags: Vec<String>,
+    /// The message before it changed, from `#|` comments, which fuzzy entries have
+    pub previous: Option<PreviousMessage>,
+    pub msgctxt: Option<String>,
+    pub msgid: String,
+    pub msgid_plural: Option<String>,
+    /// The `msgstr`, or each `msgstr[n]` of a plural message
+    pub msgstr: Vec<String>,
+    /// Whether the entry is commented out with `#~`, as its message isn't used any more
+    pub obsolete: bool,
+}
+impl Entry {
+    pub fn convert_to_string(&self, options: &FormatOptions) -> String {
+        let mut result = String::new();
+        for comment in &self.translator_comments {
+            result.push_str(&format_po_comment(&' ', comment, options));
+        }
+        for comment in &self.extracted_comments {
+            result.push_str(&format_po_comment(&'.', comment, options));
+        }
+        result.push_str(&format_po_references(&self.references, options));
+        result.push_str(&format_po_flags(&self.flags));
+
+        let mut keyword = |prefix: &str, key: &str, value: &str| {
+            // The prefix takes up some of the width
+            let options = FormatOptions {
+                width: options.width.saturating_sub(prefix.len()),
+                ..options.clone()
+            };
+            for line in format_po_message(key, value, &options).lines() {
+                result.push_str(prefix);
+                result.push_str(line);
+                result.push('\n');
+            }
+        };
+        let prefix = match self.obsolete {
+            true => "#~ ",
+            false => "",
+        };
+        if let Some(previous) = &self.previous {
+            let prefix = match self.obsolete {
+                true => "#~| ",
+                false => "#| ",
+            };
+            for (key, value) in [
+                ("msgctxt", &previous.msgctxt),
+                ("msgid", &previous.msgid),
+                ("msgid_plural", &previous.msgid_plural),
+            ] {
+                if let Some(value) = value {
+                    keyword(prefix, key, value);
+                }
+            }
+        }
+        if let Some(msgctxt) = &self.msgctxt {
+            keyword(prefix, "msgctxt", msgctxt);
+        }
+        keyword(prefix, "msgid", &self.msgid);
+        match &self.msgid_plural {
+            Some(msgid_plural) => {
+                keyword(prefix, "msgid_plural", msgid_plural);
+                for (index, msgstr) in self.msgstr.iter().enumerate() {
+                    keyword(prefix, &format!("msgstr[{}]", index), msgstr);
+                }
+            }
+            None => {
+                for msgstr in &self.msgstr {
+                    keyword(prefix, "msgstr", msgstr);
+                }
+            }
+        }
+        result
+    }
+}
+
+/// The message an entry had before the source changed, to show translators what changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreviousMessage {
+    pub msgctxt: Option<String>,
+    pub msgid: Option<String>,
+    pub msgid_plural: Option<String>,
+}
+
+/// Why a PO file can't be read, and where. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+impl ParseError {
+    fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Reads a PO or POT file
+pub fn parse(source: &str) -> Result<Catalog, ParseError> {
+    let mut parser = Parser::default();
+    for (index, line) in source
+        .strip_prefix('\u{feff}')
+        .unwrap_or(source)
+        .lines()
+        .enumerate()
+    {
+        parser.parse_line(index + 1, line)?;
+    }
+    parser.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keyword {
+    Msgctxt,
+    Msgid,
+    MsgidPlural,
+    /// `msgstr`, or `msgstr[n]` with the index
+    Msgstr(Option<usize>),
+}
+impl Keyword {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "msgctxt" => Some(Self::Msgctxt),
+            "msgid" => Some(Self::Msgid),
+            "msgid_plural" => Some(Self::MsgidPlural),
+            "msgstr" => Some(Self::Msgstr(None)),
+            name => {
+                let index = name.strip_prefix("msgstr[")?.strip_suffix(']')?;
+                Some(Self::Msgstr(Some(index.parse().ok()?)))
+            }
+        }
+    }
+}
+
+/// Where the strings on the following lines are added
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    keyword: Keyword,
+    previous: bool,
+    obsolete: bool,
+}
+
+#[derive(Debug, Default)]
+struct Parser {
+    catalog: Catalog,
+    entry: Entry,
+    /// The line of the entry's msgid, once it has one
+    msgid_line: Option<usize>,
+    /// The line of the entry's first comment, for entries that don't have a msgid yet
+    comment_line: Option<usize>,
+    field: Option<Field>,
+}
+impl Parser {
+    fn parse_line(&mut self, number: usize, line: &str) -> Result<(), ParseError> {
+        let text = line.trim_start();
+        let column = line.chars().count() - text.chars().count() + 1;
+        if text.is_empty() {
+            // Comments before a blank line still belong to the next message
+            if self.msgid_line.is_some() {
+                self.end_entry()?;
+            }
+            return Ok(());
+        }
+
+        if let Some(rest) = text.strip_prefix("#~") {
+            return match rest.strip_prefix('|') {
+                Some(rest) => self.parse_keyword_line(number, column + 3, rest, true, true),
+                None => self.parse_keyword_line(number, column + 2, rest, false, true),
+            };
+        }
+        if let Some(rest) = text.strip_prefix("#|") {
+            return self.parse_keyword_line(number, column + 2, rest, true, false);
+        }
+        if let Some(rest) = text.strip_prefix('#') {
+            return self.parse_comment(number, rest);
+        }
+        self.parse_keyword_line(number, column, text, false, false)
+    }
+
+    fn parse_comment(&mut self, number: usize, comment: &str) -> Result<(), ParseError> {
+        // Comments start the next entry
+        if self.msgid_line.is_some() {
+            self.end_entry()?;
+        }
+        self.comment_line.get_or_insert(number);
+        self.field = None;
+
+        let text = |comment: &str| comment.strip_prefix(' ').unwrap_or(comment).to_string();
+        let entry = &mut self.entry;
+        match comment.chars().next() {
+            Some('.') => entry.extracted_comments.push(text(&comment[1..])),
+            Some(':') => entry
+                .references
+                .extend(comment[1..].split_whitespace().map(Reference::parse)),
+            Some(',') => entry.flags.extend(
+                comment[1..]
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|flag| !flag.is_empty())
+                    .map(String::from),
+            ),
+            _ => entry.translator_comments.push(text(comment)),
+        }
+        Ok(())
+    }
+
+    /// Parses a line with a keyword and a string, like `msgid "Save"`, or a string continuing
+    /// the previous one. `#|` and `#~` have been removed from the line.
+    fn parse_keyword_line(
+        &mut self,
+        number: usize,
+        column: usize,
+        line: &str,
+        previous: bool,
+        obsolete: bool,
+    ) -> Result<(), ParseError> {
+        let text = line.trim_start();
+        let column = column + line.chars().count() - text.chars().count();
+        let error = |column: usize, message: String| Err(ParseError::new(number, column, message));
+
+        if text.starts_with('"') {
+            let value = parse_string(text, number, column)?;
+            return match self.field {
+                Some(field) if field.obsolete != obsolete => error(
+                    column,
+                    "Obsolete entries must have `#~` before each line".into(),
+                ),
+                Some(field) if field.previous == previous => {
+                    self.field_value(field).push_str(&value);
+                    Ok(())
+                }
+                _ => error(
+                    column,
+                    "Expected a keyword like `msgid` before a string".into(),
+                ),
+            };
+        }
+
+        let name_end = text
+            .find(|c: char| c.is_whitespace() || c == '"')
+            .unwrap_or(text.len());
+        let name = &text[..name_end];
+        let Some(keyword) = Keyword::parse(name) else {
+            return error(column, format!("Unknown keyword `{}`", name));
+        };
+        let rest = &text[name_end..];
+        let string = rest.trim_start();
+        let string_column =
+            column + name.chars().count() + rest.chars().count() - string.chars().count();
+        let value = parse_string(string, number, string_column)?;
+
+        if previous {
+            // `#|` lines come before the message
+            if self.msgid_line.is_some() {
+                self.end_entry()?;
+            }
+            self.comment_line.get_or_insert(number);
+            let message = self.entry.previous.get_or_insert_with(Default::default);
+            match keyword {
+                Keyword::Msgctxt => message.msgctxt = Some(value),
+                Keyword::Msgid => message.msgid = Some(value),
+                Keyword::MsgidPlural => message.msgid_plural = Some(value),
+                Keyword::Msgstr(_) => {
+                    return error(column, "Previous messages don't have a msgstr".into())
+                }
+            }
+            self.field = Some(Field {
+                keyword,
+                previous,
+                obsolete,
+            });
+            return Ok(());
+        }
+
+        if matches!(keyword, Keyword::Msgctxt | Keyword::Msgid) && self.msgid_line.is_some() {
+            self.end_entry()?;
+        }
+        if self.msgid_line.is_some() && self.entry.obsolete != obsolete {
+            return error(
+                column,
+                "Obsolete entries must have `#~` before each line".into(),
+            );
+        }
+        let entry = &mut self.entry;
+        entry.obsolete = obsolete;
+        match keyword {
+            Keyword::Msgctxt if entry.msgctxt.is_some() => {
+                return error(column, "Expected `msgid` after `msgctxt`".into());
+            }
+            Keyword::Msgctxt => entry.msgctxt = Some(value),
+            Keyword::Msgid => {
+                entry.msgid = value;
+                self.msgid_line = Some(number);
+            }
+            _ if self.msgid_line.is_none() => {
+                return error(column, format!("Expected `msgid` before `{}`", name));
+            }
+            Keyword::MsgidPlural if entry.msgid_plural.is_some() || !entry.msgstr.is_empty() => {
+                return error(column, "Expected `msgid_plural` right after `msgid`".into());
+            }
+            Keyword::MsgidPlural => entry.msgid_plural = Some(value),
+            Keyword::Msgstr(None) if entry.msgid_plural.is_some() => {
+                return error(column, "Expected `msgstr[n]` for a plural message".into());
+            }
+            Keyword::Msgstr(None) if !entry.msgstr.is_empty() => {
+                return error(column, "Expected a single `msgstr`".into());
+            }
+            Keyword::Msgstr(Some(_)) if entry.msgid_plural.is_none() => {
+                return error(column, "`msgstr[n]` is only for plural messages".into());
+            }
+            Keyword::Msgstr(Some(index)) if index != entry.msgstr.len() => {
+                return error(column, format!("Expected `msgstr[{}]`", entry.msgstr.len()));
+            }
+            Keyword::Msgstr(_) => entry.msgstr.push(value),
+        }
+        self.field = Some(Field {
+            keyword,
+            previous,
+            obsolete,
+        });
+        Ok(())
+    }
+
+    fn field_value(&mut self, field: Field) -> &mut String {
+        let entry = &mut self.entry;
+        if field.previous {
+            let message = entry.previous.get_or_insert_with(Default::default);
+            return match field.keyword {
+                Keyword::Msgctxt => message.msgctxt.get_or_insert_with(String::new),
+                Keyword::MsgidPlural => message.msgid_plural.get_or_insert_with(String::new),
+                _ => message.msgid.get_or_insert_with(String::new),
+            };
+        }
+        match field.keyword {
+            Keyword::Msgctxt => entry.msgctxt.get_or_insert_with(String::new),
+            Keyword::Msgid => &mut entry.msgid,
+            Keyword::MsgidPlural => entry.msgid_plural.get_or_insert_with(String::new),
+            // A string was added when the keyword was read
+            Keyword::Msgstr(_) => entry.msgstr.last_mut().unwrap(),
+        }
+    }
+
+    fn end_entry(&mut self) -> Result<(), ParseError> {
+        let line = self.msgid_line.unwrap_or_default();
+        if self.entry.msgstr.is_empty() {
+            return Err(ParseError::new(line, 1, "Expected a msgstr for this msgid"));
+        }
+        self.catalog.entries.push(std::mem::take(&mut self.entry));
+        self.msgid_line = None;
+        self.comment_line = None;
+        self.field = None;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Catalog, ParseError> {
+        match (self.msgid_line, self.comment_line) {
+            (Some(_), _) => self.end_entry()?,
+            (None, Some(line)) => {
+                return Err(ParseError::new(
+                    line,
+                    1,
+                    "Expected a message after comments",
+                ));
+            }
+            (None, None) => {}
+        }
+        Ok(self.catalog)
+    }
+}
+
+/// Reads a quoted string and its escape sequences, which must end the line
+fn parse_string(text: &str, line: usize, column: usize) -> Result<String, ParseError> {
+    let error =
+        |offset: usize, message: String| Err(ParseError::new(line, column + offset, message));
+    let mut chars = text.chars().enumerate().peekable();
+    if chars.next().map(|(_, c)| c) != Some('"') {
+        return error(0, "Expected a string".into());
+    }
+
+    let mut value = String::new();
+    // Octal and hexadecimal escapes are bytes, and consecutive ones make up UTF-8 characters
+    let mut bytes = Vec::new();
+    let mut bytes_offset = 0;
+    loop {
+        let Some((offset, c)) = chars.next() else {
+            return error(
+                text.chars().count(),
+                "Expected `\"` to end the string".into(),
+            );
+        };
+        let byte_escape = c == '\\' && matches!(chars.peek(), Some((_, '0'..='7' | 'x')));
+        if !byte_escape && !bytes.is_empty() {
+            match String::from_utf8(std::mem::take(&mut bytes)) {
+                Ok(decoded) => value.push_str(&decoded),
+                Err(_) => return error(bytes_offset, "Escape sequences aren't valid UTF-8".into()),
+            }
+        }
+        if byte_escape && bytes.is_empty() {
+            bytes_offset = offset;
+        }
+        match c {
+            '"' => break,
+            '\\' => {
+                let Some((_, escape)) = chars.next() else {
+                    return error(offset, "Expected an escape sequence after `\\`".into());
+                };
+                match escape {
+                    '\\' | '"' | '\'' | '?' => value.push(escape),
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    'a' => value.push('\u{7}'),
+                    'b' => value.push('\u{8}'),
+                    'v' => value.push('\u{b}'),
+                    'f' => value.push('\u{c}'),
+                    '0'..='7' => {
+                        let mut code = escape.to_digit(8).unwrap();
+                        for _ in 0..2 {
+                            match chars.peek().and_then(|(_, c)| c.to_digit(8)) {
+                                Some(digit) => {
+                                    code = code * 8 + digit;
+                                    chars.next();
+                                }
+                                None => break,
+                            }
+                        }
+                        match u8::try_from(code) {
+                            Ok(byte) => bytes.push(byte),
+                            Err(_) => return error(offset, "Invalid octal escape sequence".into()),
+                        }
+                    }
+                    'x' => {
+                        let mut code = 0;
+                        let mut digits = 0;
+                        while digits < 2 {
+                            let Some(digit) = chars.peek().and_then(|(_, c)| c.to_digit(16)) else {
+                                break;
+                            };
+                            code = code * 16 + digit;
+                            digits += 1;
+                            chars.next();
+                        }
+                        if digits == 0 {
+                            return error(offset, "Invalid `\\x` escape sequence".into());
+                        }
+                        bytes.push(code as u8);
+                    }
+                    escape => {
+                        return error(offset, format!("Unknown escape sequence `\\{}`", escape))
+                    }
+                }
+            }
+            c => value.push(c),
+        }
+    }
+
+    match chars.find(|(_, c)| !c.is_whitespace()) {
+        Some((offset, _)) => error(offset, "Unexpected text after the string".into()),
+        None => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        header::{Header, HeaderOptions},
+        pot::{POTMessageID, POT},
+    };
+
+    const PO: &str = r#"# French translations
+# Copyright (C) 2024 Acme Inc.
+#
+msgid ""
+msgstr ""
+"Project-Id-Version: app 1.0\n"
+"POT-Creation-Date: 2024-02-29 13:05+0000\n"
+"Language: fr\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Plural-Forms: nplurals=2; plural=(n > 1);\n"
+
+# Keep it short
+#. The button that saves the document
+#: src/app.ts:12 src/toolbar/Save.tsx:4:7
+#, fuzzy, c-format
+#| msgid "Save %s"
+msgctxt "toolbar"
+msgid "Save %d"
+msgstr "Enregistrer %d"
+
+#: src/files.ts:8
+msgid "One file"
+msgid_plural "%d files"
+msgstr[0] "Un fichier"
+msgstr[1] "%d fichiers"
+
+msgid ""
+"Dear customer,\n"
+"Your order has shipped. It \"should\" arrive within three to five business "
+"days.\n"
+msgstr ""
+"Cher client,\n"
+"Votre commande a été expédiée.\tMerci\\\n"
+
+#~| msgid "Old"
+#~ msgid "Removed"
+#~ msgstr "Supprimé"
+"#;
+
+    /// Written by `msgcat`
+    const MSGCAT_PO: &str = r#"# German translations for our app.
+# Copyright (C) 2024 Acme Inc.
+#
+msgid ""
+msgstr ""
+"Project-Id-Version: app 1.0\n"
+"Language: de\n"
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=UTF-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. TRANSLATORS: shown when the upload is done
+#: src/upload.ts:42
+#, fuzzy, c-format
+#| msgid "Uploaded %d file"
+#| msgid_plural "Uploaded %d files"
+msgid "Uploaded %d file to the shared folder that everyone in the team can see"
+msgid_plural ""
+"Uploaded %d files to the shared folder that everyone in the team can see"
+msgstr[0] ""
+"%d Datei in den freigegebenen Ordner hochgeladen, den jeder im Team sehen "
+"kann"
+msgstr[1] ""
+"%d Dateien in den freigegebenen Ordner hochgeladen, den jeder im Team sehen "
+"kann"
+
+#: src/menu.ts:3 src/menu.ts:17
+#, no-c-format
+msgctxt "menu"
+msgid "100% done"
+msgstr "100% fertig"
+
+#~ msgid "Removed"
+#~ msgstr "Entfernt"
+"#;
+
+    #[test]
+    fn parses_entries() {
+        let catalog = parse(PO).unwrap();
+        assert_eq!(catalog.entries.len(), 5);
+        assert_eq!(
+            catalog.header().unwrap().translator_comments,
+            vec!["French translations", "Copyright (C) 2024 Acme Inc.", ""]
+        );
+        assert_eq!(catalog.header_field("Language"), Some("fr"));
+        assert_eq!(
+            catalog.header_field("POT-Creation-Date"),
+            Some("2024-02-29 13:05+0000")
+        );
+        assert_eq!(catalog.header_field("Last-Translator"), None);
+
+        assert_eq!(
+            catalog.entries[1],
+            Entry {
+                translator_comments: vec!["Keep it short".to_string()],
+                extracted_comments: vec!["The button that saves the document".to_string()],
+                references: BTreeSet::from([
+                    Reference::new("src/app.ts", Some(12), None),
+                    Reference::new("src/toolbar/Save.tsx", Some(4), Some(7)),
+                ]),
+                flags: vec!["fuzzy".to_string(), "c-format".to_string()],
+                previous: Some(PreviousMessage {
+                    msgid: Some("Save %s".to_string()),
+                    ..Default::default()
+                }),
+                msgctxt: Some("toolbar".to_string()),
+                msgid: "Save %d".to_string(),
+                msgstr: vec!["Enregistrer %d".to_string()],
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            catalog.entries[2].msgstr,
+            vec!["Un fichier".to_string(), "%d fichiers".to_string()]
+        );
+        assert_eq!(
+            catalog.entries[3].msgid,
+            "Dear customer,\nYour order has shipped. It \"should\" arrive within three to five business days.\n"
+        );
+        assert_eq!(
+            catalog.entries[3].msgstr,
+            vec!["Cher client,\nVotre commande a été expédiée.\tMerci\\\n".to_string()]
+        );
+        assert!(catalog.entries[4].obsolete);
+        assert_eq!(
+            catalog.entries[4].previous.as_ref().unwrap().msgid,
+            Some("Old".to_string())
+        );
+    }
+
+    #[test]
+    fn decodes_escaped_bytes_as_utf8() {
+        let catalog = parse(
+            "msgid \"caf\\303\\251 \\xe2\\x82\\xac\\101\"\nmsgstr \"\\360\\237\\215\\260\"\n",
+        )
+        .unwrap();
+        assert_eq!(catalog.entries[0].msgid, "café €A");
+        assert_eq!(catalog.entries[0].msgstr, vec!["🍰"]);
+    }
+
+    #[test]
+    fn round_trips_with_the_writer() {
+        let catalog = parse(PO).unwrap();
+        assert_eq!(catalog.convert_to_string(&FormatOptions::default()), PO);
+
+        let mut pot = POT::new(None);
+        let meta = pot.add_message(
+            None,
+            POTMessageID {
+                msgctx: Some("menu".to_string()),
+                msgid: "You won %d coffee.".to_string(),
+                msgid_plural: Some("You won %d coffees.".to_string()),
+            },
+        );
+        meta.references
+            .insert(Reference::new("src/index.jsx", Some(15), None));
+        meta.extracted_comments.insert(
+            "A long comment that explains to translators which coffees were won, and why"
+                .to_string(),
+        );
+        meta.flags.insert("c-format".to_string());
+        meta.flags.insert("no-wrap".to_string());
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Escapes: \\ \" \t \u{7} \u{1b}\nand a line break".to_string(),
+                ..Default::default()
+            },
+        );
+        let header = Header::new(
+            &HeaderOptions {
+                comments: vec!["Our app".to_string()],
+                package_name: Some("app".to_string()),
+                pot_creation_date: Some("2024-02-29 13:05+0000".to_string()),
+                ..Default::default()
+            },
+            std::time::SystemTime::now(),
+        );
+        for options in [
+            FormatOptions::default(),
+            FormatOptions {
+                width: 30,
+                ..Default::default()
+            },
+        ] {
+            let written = pot.domains["default"].convert_to_string(&header, &options);
+            assert!(written.contains("#, c-format, no-wrap\n"));
+            let catalog = parse(&written).unwrap();
+            assert_eq!(catalog.convert_to_string(&options), written);
+            assert_eq!(
+                catalog.entries[1].msgid,
+                "Escapes: \\ \" \t \u{7} \u{1b}\nand a line break"
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_files_written_by_msgcat() {
+        let catalog = parse(MSGCAT_PO).unwrap();
+        assert_eq!(catalog.entries[1].flags, vec!["fuzzy", "c-format"]);
+        assert_eq!(
+            catalog.convert_to_string(&FormatOptions::default()),
+            MSGCAT_PO
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_their_position() {
+        let error = |source: &str| parse(source).unwrap_err().to_string();
+        assert_eq!(
+            error("msgid \"Save\"\nmsgstr \"Enregistrer\n"),
+            "2:20: Expected `\"` to end the string"
+        );
+        assert_eq!(
+            error("msgid \"Save\"\n  msgstr \"\\q\"\n"),
+            "2:11: Unknown escape sequence `\\q`"
+        );
+        assert_eq!(
+            error("msgid \"Save\"\nmsgstr \"\" extra\n"),
+            "2:11: Unexpected text after the string"
+        );
+        assert_eq!(
+            error("msgid \"Save\"\nmsgstring \"\"\n"),
+            "2:1: Unknown keyword `msgstring`"
+        );
+        assert_eq!(
+            error("msgid \"Save\"\n\nmsgid \"Cancel\"\nmsgstr \"\"\n"),
+            "1:1: Expected a msgstr for this msgid"
+        );
+        assert_eq!(
+            error("msgid \"File\"\nmsgid_plural \"Files\"\nmsgstr[1] \"\"\n"),
+            "3:1: Expected `msgstr[0]`"
+        );
+        assert_eq!(
+            error("msgid \"File\"\nmsgstr[0] \"\"\n"),
+            "2:1: `msgstr[n]` is only for plural messages"
+        );
+        assert_eq!(
+            error("msgid \"caf\\303\"\nmsgstr \"\"\n"),
+            "1:11: Escape sequences aren't valid UTF-8"
+        );
+        assert_eq!(
+            error("msgid \"\\777\"\nmsgstr \"\"\n"),
+            "1:8: Invalid octal escape sequence"
+        );
+        assert_eq!(
+            error("msgid \"Save\"\nmsgstr \"Enregistrer\"\n#~ \" le fichier\"\n"),
+            "3:4: Obsolete entries must have `#~` before each line"
+        );
+        assert_eq!(
+            error("#~ msgid \"Save\"\n\" the file\"\n#~ msgstr \"\"\n"),
+            "2:1: Obsolete entries must have `#~` before each line"
+        );
+        assert_eq!(
+            error("\"Save\"\n"),
+            "1:1: Expected a keyword like `msgid` before a string"
+        );
+        assert_eq!(
+            error("#. A comment\n"),
+            "1:1: Expected a message after comments"
+        );
+    }
+}
@@ -1,7 +1,17 @@
 // See https://www.gnu.org/software/gettext/manual/html_node/PO-Files.html for details about a POT file format
 
 use itertools::Itertools;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{
+    header::{creation_date, Header},
+    po::{Catalog, Entry},
+};
 
 /// An individual message in a POT file
 #[derive(Debug, PartialEq, Eq, Hash, Default, Ord, PartialOrd)]
@@ -10,38 +20,122 @@ pub struct POTMessageID {
     pub msgid: String,
     pub msgid_plural: Option<String>,
 }
-impl POTMessageID {
-    fn convert_to_string(&self) -> String {
-        let mut result = String::new();
 
-        if let Some(ctx) = &self.msgctx {
-            result.push_str(&format_po_message("msgctxt", ctx));
-            result.push('\n');
+/// Where a message is used in the source, written as `path:line:column` in `#:` comments.
+///
+/// References sort naturally, so that `file.ts:2` comes before `file.ts:10` and `page2.ts`
+/// before `page10.ts`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    /// The path of the file, always with forward slashes
+    pub path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+impl Reference {
+    pub fn new(path: &str, line: Option<usize>, column: Option<usize>) -> Self {
+        Self {
+            path: path.replace('\\', "/"),
+            line,
+            column,
         }
-        result.push_str(&format_po_message("msgid", &self.msgid));
-        result.push('\n');
+    }
 
-        if let Some(msgid_plural) = &self.msgid_plural {
-            result.push_str(&format_po_message("msgid_plural", msgid_plural));
-            result.push('\n');
-            result.push_str(&format_po_message("msgstr[0]", ""));
-            result.push('\n');
-            result.push_str(&format_po_message("msgstr[1]", ""));
-        } else {
-            result.push_str(&format_po_message("msgstr", ""));
+    /// Reads a reference as written in `#:` comments, like `src/app.ts:12` or `src/app.ts:12:5`
+    pub fn parse(reference: &str) -> Self {
+        fn number(text: &str) -> Option<(&str, usize)> {
+            let (rest, number) = text.rsplit_once(':')?;
+            Some((rest, number.parse().ok()?))
         }
-
-        result
+        match number(reference) {
+            Some((rest, last)) => match number(rest) {
+                Some((path, line)) => Self::new(path, Some(line), Some(last)),
+                None => Self::new(rest, Some(last), None),
+            },
+            None => Self::new(reference, None, None),
+        }
+    }
+}
+impl Ord for Reference {
+    fn cmp(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.path, &other.path)
+            .then_with(|| self.line.cmp(&other.line))
+            .then_with(|| self.column.cmp(&other.column))
+    }
+}
+impl PartialOrd for Reference {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        Ok(())
     }
 }
 
+/// Compares strings with the numbers in them compared by value, falling back to a plain
+/// comparison so that the order stays total, e.g. for `page01` and `page1`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let chunks = |text: &str| {
+        text.chars()
+            .chunk_by(char::is_ascii_digit)
+            .into_iter()
+            .map(|(_, chunk)| chunk.collect::<String>())
+            .collect::<Vec<_>>()
+    };
+    let ordering = chunks(a)
+        .iter()
+        .zip(chunks(b).iter())
+        .map(|(a, b)| {
+            match (
+                a.starts_with(|c: char| c.is_ascii_digit()),
+                b.starts_with(|c: char| c.is_ascii_digit()),
+            ) {
+                (true, true) => {
+                    let (a, b) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+                    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+                }
+                _ => a.cmp(b),
+            }
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal);
+    ordering.then_with(|| a.cmp(b))
+}
+
+/// Where a call was found, to write messages in the order they're found in the source.
+/// Unlike a counter, this doesn't depend on the order files are parsed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SourcePosition {
+    /// The index of the file in the order files are walked
+    pub file: usize,
+    /// The byte offset of the call in the file
+    pub offset: u32,
+}
+
+/// The extracted comment of messages marked with a no-op marker like `N_`
+const DEFERRED_COMMENT: &str = "Marked for deferred translation";
+
 /// Metadata about a message in a POT file that doesn't affect it's uniqueness
 #[derive(Debug)]
 pub struct POTMessageMeta {
-    pub references: BTreeSet<String>,
+    pub references: BTreeSet<Reference>,
     pub translator_comments: BTreeSet<String>,
     pub extracted_comments: BTreeSet<String>,
     pub flags: BTreeSet<String>,
+    /// The first call of the message, for `--sort-output=source-order`
+    pub first_seen: Option<SourcePosition>,
+    /// Whether the message was marked with a no-op marker like `N_`, to be translated later.
+    /// Translators are told with an extracted comment.
+    pub deferred: bool,
 }
 impl POTMessageMeta {
     fn new() -> Self {
@@ -50,32 +144,90 @@ impl POTMessageMeta {
             translator_comments: BTreeSet::new(),
             extracted_comments: BTreeSet::new(),
             flags: BTreeSet::new(),
+            first_seen: None,
+            deferred: false,
         }
     }
 
-    fn convert_to_string(&self) -> String {
-        let mut result = String::new();
+    /// Records a call of the message, keeping the first one
+    pub fn add_position(&mut self, position: SourcePosition) {
+        self.first_seen = Some(
+            self.first_seen
+                .map_or(position, |first| first.min(position)),
+        );
+    }
+
+    /// The PO entry of the message, to be written like any other entry
+    fn to_entry(&self, message: &POTMessageID, options: &FormatOptions) -> Entry {
+        let text = |msg: &str| {
+            if options.collapse_newlines {
+                msg.replace("\r\n", " ").replace(['\r', '\n'], " ")
+            } else {
+                msg.to_string()
+            }
+        };
         let POTMessageMeta {
             references,
             translator_comments,
             extracted_comments,
             flags,
+            first_seen: _,
+            deferred,
         } = self;
-        {
-            for comment in translator_comments {
-                result.push_str(&format_po_comment(&' ', comment));
-            }
-            for comment in extracted_comments {
-                result.push_str(&format_po_comment(&'.', comment));
-            }
-            for reference in references {
-                result.push_str(&format!("#: {}\n", reference));
-            }
-            for flag in flags {
-                result.push_str(&format_po_comment(&',', flag));
-            }
+        let mut extracted_comments = extracted_comments.iter().cloned().collect::<Vec<_>>();
+        if *deferred {
+            extracted_comments.push(DEFERRED_COMMENT.to_string());
+        }
+        Entry {
+            translator_comments: translator_comments.iter().cloned().collect(),
+            extracted_comments,
+            references: references.clone(),
+            flags: flags.iter().cloned().collect(),
+            msgctxt: message.msgctx.as_deref().map(text),
+            msgid: text(&message.msgid),
+            msgid_plural: message.msgid_plural.as_deref().map(text),
+            msgstr: match message.msgid_plural {
+                Some(_) => vec![String::new(), String::new()],
+                None => vec![String::new()],
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// The order messages are written in, like xgettext's `--sort-output` and `--sort-by-file`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortOutput {
+    /// By context and msgid
+    #[default]
+    Msgid,
+    /// By the first reference, so that messages from the same file and screen are together
+    File,
+    /// In the order they're first found, walking files in order of their names
+    SourceOrder,
+}
+
+/// How messages are written in POT files
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Write line breaks in messages as spaces, like earlier versions did. The msgid then
+    /// doesn't match the string the code looks up.
+    pub collapse_newlines: bool,
+    /// The number of display columns lines are wrapped at
+    pub width: usize,
+    /// Whether long messages and comments are wrapped. Messages are still broken after line
+    /// breaks, and references are always packed to fit the width, like msgcat's `--no-wrap`.
+    pub wrap: bool,
+    pub sort: SortOutput,
+}
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            collapse_newlines: false,
+            width: DEFAULT_WIDTH,
+            wrap: true,
+            sort: SortOutput::default(),
         }
-        result
     }
 }
 
@@ -84,35 +236,69 @@ pub struct POTFile {
     messages: HashMap<POTMessageID, POTMessageMeta>,
 }
 impl POTFile {
-    pub fn convert_to_string(&self) -> String {
-        let mut result = String::new();
+    pub fn convert_to_string(&self, header: &Header, options: &FormatOptions) -> String {
+        let header = Entry {
+            translator_comments: header.comments.clone(),
+            msgstr: vec![header.msgstr()],
+            ..Default::default()
+        };
 
-        // Add headers
-        result.push_str(
-            r#"msgid ""
-msgstr ""
-"Content-Type: text/plain; charset=utf-8\n"
-"Plural-Forms: nplurals=2; plural=(n != 1);\n"
-"#,
-        );
-
-        for message in self.messages.keys().sorted() {
-            let meta = self.messages.get(message).unwrap();
-            result.push('\n');
-            result.push_str(&meta.convert_to_string());
-            result.push_str(&message.convert_to_string());
-            result.push('\n');
+        let mut messages = self.messages.iter().collect::<Vec<_>>();
+        match options.sort {
+            SortOutput::Msgid => messages.sort_by_key(|&(message, _)| message),
+            SortOutput::File => {
+                messages.sort_by_key(|&(message, meta)| (meta.references.first(), message))
+            }
+            SortOutput::SourceOrder => {
+                messages.sort_by_key(|&(message, meta)| (meta.first_seen, message))
+            }
         }
-        result
+        let entries = messages
+            .into_iter()
+            .map(|(message, meta)| meta.to_entry(message, options));
+        Catalog {
+            entries: std::iter::once(header).chain(entries).collect(),
+        }
+        .convert_to_string(options)
     }
     pub fn new() -> Self {
         Self {
             messages: HashMap::new(),
         }
     }
+
+    /// Writes the file like `convert_to_string`, but keeping the `POT-Creation-Date` of an
+    /// existing file, so that the file can be compared with it without the date being a change
+    pub fn convert_to_string_like(
+        &self,
+        existing: &str,
+        header: &Header,
+        options: &FormatOptions,
+    ) -> String {
+        let header = match (&header.pot_creation_date, creation_date(existing)) {
+            (Some(_), Some(date)) => &Header {
+                pot_creation_date: Some(date),
+                ..header.clone()
+            },
+            _ => header,
+        };
+        self.convert_to_string(header, options)
+    }
+
+    /// Whether an existing file has the same content, apart from its `POT-Creation-Date`, so that
+    /// it doesn't have to be written again
+    pub fn is_unchanged(&self, existing: &str, header: &Header, options: &FormatOptions) -> bool {
+        self.convert_to_string_like(existing, header, options) == existing
+    }
+
+    #[cfg(test)]
+    pub fn get(&self, message: &POTMessageID) -> Option<&POTMessageMeta> {
+        self.messages.get(message)
+    }
 }
 
 #[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub struct POT {
     default_domain: String,
     pub domains: BTreeMap<String, POTFile>,
@@ -143,48 +329,202 @@ impl POT {
     pub fn to_string(&self, domain: Option<&str>) -> Option<String> {
         self.domains
             .get(domain.unwrap_or(&self.default_domain))
-            .map(|file| file.convert_to_string())
+            .map(|file| file.convert_to_string(&Header::default(), &FormatOptions::default()))
     }
 }
 
-const MAX_LINE_LENGTH: usize = 80;
+/// The width of lines in POT files, like xgettext and msgcat
+pub const DEFAULT_WIDTH: usize = 79;
+/// The narrowest width msgcat accepts
+pub const MIN_WIDTH: usize = 20;
 
-fn format_po_message(key: &str, msg: &str) -> std::string::String {
-    // If line will exceed max length (including quotes & space)
-    let msg_escaped = msg
-        .replace('"', "\\\"")
-        .replace("\r\n", " ")
-        .replace(['\r', '\n'], " ");
-    if msg_escaped.len() > MAX_LINE_LENGTH - key.len() - 3 {
-        let mut result = String::new();
-        result.push_str(&format!("{} \"\"\n", key));
-        let mut line = String::new();
-        for word in msg_escaped.split(' ') {
-            // minus 3 for the quotes and trailing space
-            if (line.len() + word.len() + 1) > (MAX_LINE_LENGTH - 3) {
-                result.push_str(&format!("\"{}\"\n", line));
-                line = String::new();
+/// Writes a PO string. Like msgcat, a string that doesn't fit on the first line, or that has
+/// line breaks, starts with an empty string and continues with a line per line break. Lines are
+/// broken where Unicode allows, mostly after spaces, to fit the width.
+pub fn format_po_message(key: &str, msg: &str, options: &FormatOptions) -> std::string::String {
+    let multiline = msg.trim_end_matches('\n').contains('\n');
+    let msg_escaped = escape_po_string(msg);
+    // The key, a space and the quotes
+    let first_width = options.width.saturating_sub(key.len() + 3);
+    if !multiline && (!options.wrap || msg_escaped.width() <= first_width) {
+        return format!("{} \"{}\"", key, msg_escaped);
+    }
+
+    let segments = msg
+        .split_inclusive('\n')
+        .map(escaped_units)
+        .collect::<Vec<_>>();
+    // A string that can't be broken anywhere stays on the first line
+    if !multiline && wrap_units(&segments[0], first_width).len() == 1 {
+        return format!("{} \"{}\"", key, msg_escaped);
+    }
+
+    let mut result = String::new();
+    result.push_str(&format!("{} \"\"", key));
+    for units in &segments {
+        let lines = if options.wrap {
+            // Minus 2 for the quotes
+            wrap_units(units, options.width.saturating_sub(2))
+        } else {
+            vec![units.as_slice()]
+        };
+        for line in lines {
+            result.push_str("\n\"");
+            result.extend(line.iter().map(|unit| unit.text.as_str()));
+            result.push('"');
+        }
+    }
+    result
+}
+
+/// A part of an escaped string that is never broken, like a character or an escape sequence
+struct Unit {
+    text: String,
+    width: usize,
+    /// Whether a line can be broken after this unit
+    break_after: bool,
+}
+
+/// Splits a string into escaped units, with the places a line can be broken. This follows the
+/// Unicode line breaking rules that matter for messages, like msgcat: after spaces, after hyphens
+/// and slashes inside words, after `!`, `?` and `|`, around dashes, and around wide characters
+/// like CJK ideographs and emoji, except before closing or after opening punctuation.
+fn escaped_units(msg: &str) -> Vec<Unit> {
+    const NO_BREAK_BEFORE: &str =
+        "、。，．：；！？）」』】〕〉》〙〗｝ー々ぁぃぅぇぉっゃゅょゎァィゥェォッャュョヮヵヶ";
+    const NO_BREAK_AFTER: &str = "（「『【〔〈《〘〖｛";
+
+    let chars = msg.chars().collect::<Vec<_>>();
+    let wide = |c: char| c.width() == Some(2);
+    chars
+        .iter()
+        .enumerate()
+        .map(|(index, &c)| {
+            let previous = index.checked_sub(1).map(|index| chars[index]);
+            let next = chars.get(index + 1).copied();
+            let break_after = next.is_some_and(|next| match (c, next) {
+                (' ', ' ') => false,
+                (' ', _) => true,
+                (_, ' ') => false,
+                ('-', next) => previous.is_some_and(char::is_alphanumeric) && next.is_alphabetic(),
+                ('/', next) => next.is_alphabetic(),
+                ('!' | '?', next) => !next.is_whitespace() && !".,;:!?)]}'\"".contains(next),
+                ('|' | '—', _) | (_, '—') => true,
+                (c, next) if wide(c) || wide(next) => {
+                    !NO_BREAK_BEFORE.contains(next) && !NO_BREAK_AFTER.contains(c)
+                }
+                _ => false,
+            });
+            let text = escape_po_string(c.encode_utf8(&mut [0; 4]));
+            Unit {
+                width: text.width(),
+                text,
+                break_after,
+            }
+        })
+        .collect()
+}
+
+/// Breaks units into lines that fit the width where possible, at the last place each line can
+/// be broken. Spaces stay at the end of lines and count towards their width, like msgcat.
+fn wrap_units(units: &[Unit], width: usize) -> Vec<&[Unit]> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+    let mut line_width = 0;
+    let mut last_break = None;
+    while index < units.len() {
+        line_width += units[index].width;
+        if line_width > width {
+            if let Some(end) = last_break {
+                lines.push(&units[start..=end]);
+                start = end + 1;
+                index = start;
+                line_width = 0;
+                last_break = None;
+                continue;
             }
-            line.push_str(&format!("{} ", word));
         }
-        if !line.is_empty() {
-            result.push_str(&format!("\"{}\"", &line[..line.len() - 1]));
+        if units[index].break_after {
+            last_break = Some(index);
         }
-        result
-    } else {
-        format!("{} \"{}\"", key, msg_escaped)
+        index += 1;
     }
+    lines.push(&units[start..]);
+    lines
 }
 
-fn format_po_comment(prefix: &char, msg: &str) -> std::string::String {
+/// Escapes a string like C, as PO files expect
+fn escape_po_string(msg: &str) -> String {
+    let mut escaped = String::with_capacity(msg.len());
+    for c in msg.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{7}' => escaped.push_str("\\a"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{b}' => escaped.push_str("\\v"),
+            '\u{c}' => escaped.push_str("\\f"),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\{:03o}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes all flags on a single `#,` line like xgettext and msgcat, which is never wrapped
+pub fn format_po_flags<'a>(flags: impl IntoIterator<Item = &'a String>) -> String {
+    let flags = flags.into_iter().join(", ");
+    match flags.is_empty() {
+        true => String::new(),
+        false => format!("#, {}\n", flags),
+    }
+}
+
+/// Packs references on `#:` lines like xgettext, starting a new line before one would get
+/// too long. A reference longer than a line is kept whole on its own line.
+pub fn format_po_references(references: &BTreeSet<Reference>, options: &FormatOptions) -> String {
+    let mut result = String::new();
+    let mut line = String::new();
+    for reference in references {
+        let reference = reference.to_string();
+        if !line.is_empty() && line.width() + 1 + reference.width() > options.width {
+            result.push_str(&line);
+            result.push('\n');
+            line.clear();
+        }
+        if line.is_empty() {
+            line.push_str("#:");
+        }
+        line.push(' ');
+        line.push_str(&reference);
+    }
+    if !line.is_empty() {
+        result.push_str(&line);
+        result.push('\n');
+    }
+    result
+}
+
+pub fn format_po_comment(prefix: &char, msg: &str, options: &FormatOptions) -> std::string::String {
+    // Translator comments only have a space after the hash
+    let line_prefix = match prefix {
+        ' ' => "# ".to_string(),
+        prefix => format!("#{} ", prefix),
+    };
+    if msg.is_empty() {
+        return format!("{}\n", line_prefix.trim_end());
+    }
     // If line will exceed max length (including prefix, hash and space)
-    let line_prefix = format!("#{} ", prefix);
-    if msg.len() > MAX_LINE_LENGTH - line_prefix.len() {
+    if options.wrap && msg.width() > options.width.saturating_sub(line_prefix.len()) {
         let mut result = String::new();
         let mut line = String::new();
         line.push_str(&line_prefix);
         for word in msg.split_whitespace() {
-            if (line.len() + word.len() + 1) > MAX_LINE_LENGTH {
+            if line.len() > line_prefix.len() && line.width() + word.width() > options.width {
                 result.push_str(line.trim());
                 result.push('\n');
                 line = String::new();
@@ -211,10 +551,10 @@ mod tests {
         pot: &mut POT,
         domain: Option<String>,
         message: POTMessageID,
-        reference: String,
+        reference: Reference,
     ) {
         let meta = pot.add_message(domain, message);
-        meta.references.insert(reference.to_string());
+        meta.references.insert(reference);
     }
 
     #[test]
@@ -227,13 +567,15 @@ mod tests {
                 msgid: "Hello, world!".to_string(),
                 ..Default::default()
             },
-            "src/main.rs".to_string(),
+            Reference::new("src/main.rs", None, None),
         );
         assert_eq!(
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: src/main.rs
@@ -253,13 +595,15 @@ msgstr ""
                 msgid_plural: Some("%d people".to_string()),
                 ..Default::default()
             },
-            "src/main.rs".to_string(),
+            Reference::new("src/main.rs", None, None),
         );
         assert_eq!(
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: src/main.rs
@@ -282,7 +626,7 @@ msgstr[1] ""
                 msgid: "File".to_string(),
                 ..Default::default()
             },
-            "src/main.rs".to_string(),
+            Reference::new("src/main.rs", None, None),
         );
         add_message_reference(
             &mut pot,
@@ -292,13 +636,15 @@ msgstr[1] ""
                 msgid: "%d file".to_string(),
                 msgid_plural: Some("%d files".to_string()),
             },
-            "src/main.rs".to_string(),
+            Reference::new("src/main.rs", None, None),
         );
         assert_eq!(
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: src/main.rs
@@ -326,13 +672,15 @@ msgstr ""
             {
                 msgid: "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur. Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt mollit anim id est laborum.".to_string(),..Default::default()
             },
-            "src/main.rs".to_string(),
+            Reference::new("src/main.rs", None, None),
         );
         assert_eq!(
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: src/main.rs
@@ -369,7 +717,9 @@ This might tip your tool off.
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #. This is a not so long comment. However, it has a line break in it. This
@@ -390,15 +740,18 @@ msgstr ""
                 ..Default::default()
             },
         );
-        meta.references.insert(
-            "path/to/very/long/filename/that/shouldnt/be/broken/here/we/go/really/this/time/my_super_special_file_v3_FINAL_FINAL_NO_EDIT.tsx:246912631923213"
-                .to_string(),
-        );
+        meta.references.insert(Reference::new(
+            "path/to/very/long/filename/that/shouldnt/be/broken/here/we/go/really/this/time/my_super_special_file_v3_FINAL_FINAL_NO_EDIT.tsx",
+            Some(246912631923213),
+            None,
+        ));
         assert_eq!(
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: path/to/very/long/filename/that/shouldnt/be/broken/here/we/go/really/this/time/my_super_special_file_v3_FINAL_FINAL_NO_EDIT.tsx:246912631923213
@@ -415,7 +768,7 @@ msgstr ""
             None,
             POTMessageID {
                 msgid: r#"A string with a new line
-should be replaced with a space"#
+is written on two lines"#
                     .to_string(),
                 ..Default::default()
             },
@@ -434,26 +787,179 @@ should be replaced with a space"#
                 ..Default::default()
             },
         );
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Trailing new line\n".to_string(),
+                ..Default::default()
+            },
+        );
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Tab\there, C:\\path, carriage\rreturn, bell\u{7} and escape\u{1b}"
+                    .to_string(),
+                ..Default::default()
+            },
+        );
 
         assert_eq!(
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 msgid "A string double  whitespace"
 msgstr ""
 
-msgid "A string with a new line should be replaced with a space"
+msgid ""
+"A string with a new line\n"
+"is written on two lines"
 msgstr ""
 
 msgid "Special space"
 msgstr ""
+
+msgid "Tab\there, C:\\path, carriage\rreturn, bell\a and escape\033"
+msgstr ""
+
+msgid "Trailing new line\n"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn it_collapses_new_lines_for_compatibility() {
+        let mut pot = POT::new(None);
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "A string with a new line\r\nshould be replaced with a space".to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            pot.domains["default"].convert_to_string(
+                &Header::default(),
+                &FormatOptions {
+                    collapse_newlines: true,
+                    ..Default::default()
+                }
+            ),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+msgid "A string with a new line should be replaced with a space"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn it_breaks_lines_after_new_lines() {
+        let mut pot = POT::new(None);
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Dear customer,\n\nYour order has shipped and should arrive within three to five business days, depending on the carrier.\n".to_string(),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            pot.to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+msgid ""
+"Dear customer,\n"
+"\n"
+"Your order has shipped and should arrive within three to five business days, "
+"depending on the carrier.\n"
+msgstr ""
 "#
         );
     }
 
+    #[test]
+    fn it_wraps_by_display_width() {
+        let options = FormatOptions {
+            width: 30,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_po_message(
+                "msgid",
+                "中文句子，包含标点符号。还有更多的中文字符。",
+                &options
+            ),
+            r#"msgid ""
+"中文句子，包含标点符号。还有"
+"更多的中文字符。""#
+        );
+        assert_eq!(
+            format_po_message("msgid", "Emoji 🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉 party", &options),
+            r#"msgid ""
+"Emoji 🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉🎉"
+"🎉🎉 party""#
+        );
+        assert_eq!(
+            format_po_message(
+                "msgid",
+                "A state-of-the-art, well-known 10-20 range",
+                &options
+            ),
+            r#"msgid ""
+"A state-of-the-art, well-"
+"known 10-20 range""#
+        );
+        assert_eq!(
+            format_po_message("msgid", "https://example.com/some/long/path", &options),
+            r#"msgid ""
+"https://example.com/some/"
+"long/path""#
+        );
+        // Like msgcat, a string that can't be broken stays on one line
+        assert_eq!(
+            format_po_message("msgid", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", &options),
+            r#"msgid "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa""#
+        );
+    }
+
+    #[test]
+    fn it_doesnt_wrap_with_no_wrap() {
+        let options = FormatOptions {
+            width: 30,
+            wrap: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_po_message("msgid", "A long message that would be wrapped", &options),
+            r#"msgid "A long message that would be wrapped""#
+        );
+        assert_eq!(
+            format_po_message(
+                "msgid",
+                "Line breaks are still kept\nas separate lines",
+                &options
+            ),
+            r#"msgid ""
+"Line breaks are still kept\n"
+"as separate lines""#
+        );
+    }
+
     #[test]
     fn handles_duplicate_messages() {
         let mut pot = POT::new(None);
@@ -465,7 +971,7 @@ msgstr ""
                 msgid: "Hello, world!".to_string(),
                 ..Default::default()
             },
-            "src/main.rs:1".to_string(),
+            Reference::new("src/main.rs", Some(1), None),
         );
         add_message_reference(
             &mut pot,
@@ -475,7 +981,7 @@ msgstr ""
                 msgid: "Hello, world!".to_string().clone(),
                 ..Default::default()
             },
-            "src/main.rs:2".to_string(),
+            Reference::new("src/main.rs", Some(2), None),
         );
         add_message_reference(
             &mut pot,
@@ -485,24 +991,146 @@ msgstr ""
                 msgid: "Hello, world!".to_string().clone(),
                 ..Default::default()
             },
-            "src/main.rs:3".to_string(),
+            Reference::new("src/main.rs", Some(3), None),
         );
         assert_eq!(
             pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
-#: src/main.rs:1
-#: src/main.rs:2
-#: src/main.rs:3
+#: src/main.rs:1 src/main.rs:2 src/main.rs:3
 msgctxt "Ctxt"
 msgid "Hello, world!"
 msgstr ""
 "#
         );
     }
+    #[test]
+    fn sorts_and_packs_references() {
+        let mut pot = POT::new(None);
+        let meta = pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Save".to_string(),
+                ..Default::default()
+            },
+        );
+        meta.references.extend([
+            Reference::new("src/page10.ts", Some(1), None),
+            Reference::new("src/page2.ts", Some(10), None),
+            Reference::new("src/page2.ts", Some(2), None),
+            Reference::new("src\\page2.ts", Some(2), Some(8)),
+            Reference::new("src/components/toolbar/SaveButton.tsx", Some(112), None),
+            Reference::new("src/components/toolbar/SaveButton.tsx", Some(14), None),
+        ]);
+        assert_eq!(
+            pot.to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: src/components/toolbar/SaveButton.tsx:14
+#: src/components/toolbar/SaveButton.tsx:112 src/page2.ts:2 src/page2.ts:2:8
+#: src/page2.ts:10 src/page10.ts:1
+msgid "Save"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn sorts_output() {
+        let mut pot = POT::new(None);
+        for (msgid, path, line, file, offset) in [
+            ("Zoom", "src/page2.ts", 4, 1, 40),
+            ("Undo", "src/page10.ts", 1, 2, 10),
+            ("Cancel", "src/page2.ts", 1, 1, 10),
+        ] {
+            let meta = pot.add_message(
+                None,
+                POTMessageID {
+                    msgid: msgid.to_string(),
+                    ..Default::default()
+                },
+            );
+            meta.references
+                .insert(Reference::new(path, Some(line), None));
+            meta.add_position(SourcePosition { file, offset });
+        }
+        // Found again earlier in the walk
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Undo".to_string(),
+                ..Default::default()
+            },
+        )
+        .add_position(SourcePosition {
+            file: 0,
+            offset: 99,
+        });
+
+        let msgids = |sort| {
+            pot.domains["default"]
+                .convert_to_string(
+                    &Header::default(),
+                    &FormatOptions {
+                        sort,
+                        ..Default::default()
+                    },
+                )
+                .lines()
+                .filter_map(|line| line.strip_prefix("msgid \""))
+                .filter(|msgid| !msgid.starts_with('"'))
+                .map(|msgid| msgid.trim_end_matches('"').to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(msgids(SortOutput::Msgid), ["Cancel", "Undo", "Zoom"]);
+        assert_eq!(msgids(SortOutput::File), ["Cancel", "Zoom", "Undo"]);
+        assert_eq!(msgids(SortOutput::SourceOrder), ["Undo", "Cancel", "Zoom"]);
+    }
+
+    #[test]
+    fn ignores_the_creation_date_of_existing_files() {
+        let mut pot = POT::new(None);
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Save".to_string(),
+                ..Default::default()
+            },
+        );
+        let file = &pot.domains["default"];
+        let options = FormatOptions::default();
+        let header = |date: &str| Header {
+            pot_creation_date: Some(date.to_string()),
+            ..Default::default()
+        };
+        let existing = file.convert_to_string(&header("2024-01-01 00:00+0000"), &options);
+
+        assert!(file.is_unchanged(&existing, &header("2024-02-29 13:05+0000"), &options));
+        assert!(!file.is_unchanged(&existing, &Header::default(), &options));
+        pot.add_message(
+            None,
+            POTMessageID {
+                msgid: "Cancel".to_string(),
+                ..Default::default()
+            },
+        );
+        assert!(!pot.domains["default"].is_unchanged(
+            &existing,
+            &header("2024-02-29 13:05+0000"),
+            &options
+        ));
+    }
+
     #[test]
     fn it_has_correct_equality_check() {
         assert_eq!(
@@ -0,0 +1,78 @@
+use std::collections::HashSet;
+
+use swc_common::{
+    comments::{Comment, SingleThreadedComments},
+    BytePos, SourceMap, Span,
+};
+
+const DISABLE_FILE: &str = "gettext-extractor-disable-file";
+const DISABLE_NEXT_LINE: &str = "gettext-extractor-disable-next-line";
+const DISABLE: &str = "gettext-extractor-disable";
+const ENABLE: &str = "gettext-extractor-enable";
+
+/// Whether a comment is a suppression directive, which shouldn't be extracted for translators
+pub fn is_directive(comment: &Comment) -> bool {
+    matches!(
+        comment.text.split_whitespace().next(),
+        Some(DISABLE_FILE | DISABLE_NEXT_LINE | DISABLE | ENABLE)
+    )
+}
+
+/// The parts of a file where extraction is turned off by comments:
+///
+/// - `// gettext-extractor-disable-file` anywhere in the file
+/// - `// gettext-extractor-disable-next-line` for the line after the comment
+/// - `/* gettext-extractor-disable */` until the next `/* gettext-extractor-enable */`, or the end of the file
+///
+/// Like ESLint directives, a description can follow the directive, e.g.
+/// `// gettext-extractor-disable-next-line -- not gettext`.
+#[derive(Debug, Default)]
+pub struct Suppressions {
+    file: bool,
+    lines: HashSet<usize>,
+    ranges: Vec<(BytePos, Option<BytePos>)>,
+}
+impl Suppressions {
+    pub fn collect(cm: &SourceMap, comments: &SingleThreadedComments) -> Self {
+        let (leading, trailing) = comments.borrow_all();
+        let mut comments = leading
+            .values()
+            .chain(trailing.values())
+            .flatten()
+            .collect::<Vec<&Comment>>();
+        comments.sort_by_key(|comment| comment.span.lo);
+
+        let mut suppressions = Self::default();
+        for comment in comments {
+            match comment.text.split_whitespace().next() {
+                Some(DISABLE_FILE) => suppressions.file = true,
+                Some(DISABLE_NEXT_LINE) => {
+                    suppressions
+                        .lines
+                        .insert(cm.lookup_char_pos(comment.span.hi).line + 1);
+                }
+                // A disable inside a disabled range doesn't start a new one
+                Some(DISABLE) if !matches!(suppressions.ranges.last(), Some((_, None))) => {
+                    suppressions.ranges.push((comment.span.hi, None));
+                }
+                Some(ENABLE) => {
+                    if let Some((_, end @ None)) = suppressions.ranges.last_mut() {
+                        *end = Some(comment.span.lo);
+                    }
+                }
+                _ => {}
+            }
+        }
+        suppressions
+    }
+
+    /// Whether a call starting at this span shouldn't be extracted
+    pub fn is_suppressed(&self, cm: &SourceMap, span: Span) -> bool {
+        self.file
+            || self
+                .ranges
+                .iter()
+                .any(|(start, end)| span.lo >= *start && end.is_none_or(|end| span.lo < end))
+            || (!self.lines.is_empty() && self.lines.contains(&cm.lookup_char_pos(span.lo).line))
+    }
+}
@@ -0,0 +1,9 @@
+import { DOMAIN, MESSAGES } from './i18n/constants';
+import { CHECKOUT, MENU, constants } from '@i18n';
+import * as contexts from '@i18n/contexts.js';
+import { LABEL } from './missing';
+
+__dp(DOMAIN, CHECKOUT, MESSAGES.pay);
+__p(MENU, MESSAGES.save);
+__p(contexts.CHECKOUT, constants.MESSAGES.save);
+__(LABEL);
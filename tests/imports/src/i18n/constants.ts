@@ -0,0 +1,12 @@
+export const DOMAIN = 'billing';
+
+const SAVE = 'Save';
+export const MESSAGES = {
+  save: SAVE,
+  pay: 'Pay ' + 'now',
+  format: (value: string) => value,
+};
+
+export function format(value: string) {
+  return value;
+}
@@ -0,0 +1,3 @@
+export const CHECKOUT = 'checkout';
+const TOOLBAR = 'toolbar';
+export { TOOLBAR as MENU };
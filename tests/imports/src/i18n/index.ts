@@ -0,0 +1,3 @@
+export * from './contexts';
+export { DOMAIN } from './constants';
+export * as constants from './constants';
@@ -0,0 +1,3 @@
+__("Extracted");
+__(someVariable);
+__n(`${count} file`, `${count} files`, count);
@@ -0,0 +1,3 @@
+__();
+__n('one');
+__dnp('domain', 'context', 'one');
@@ -0,0 +1,2 @@
+__("Unparseable");
+const = ;
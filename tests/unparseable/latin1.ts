@@ -0,0 +1 @@
+__("Caf");
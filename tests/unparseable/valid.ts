@@ -0,0 +1 @@
+__("Extracted");
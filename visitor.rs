@@ -1,31 +1,423 @@
 use std::{
+    collections::BTreeSet,
     ops::Deref,
     path::PathBuf,
     sync::{Arc, Mutex},
 };
 
+use itertools::Itertools;
 use swc_common::{
-    comments::{Comment, Comments},
+    comments::{Comment, Comments, SingleThreadedComments},
     sync::Lrc,
 };
-use swc_common::{SourceMap, Span};
+use swc_common::{errors::SourceMapper, BytePos, FileName, SourceMap, Span, Spanned};
 use swc_ecma_ast::*;
 use swc_ecma_visit::{noop_visit_type, Visit, VisitWith};
 
-use crate::pot::{POTMessageID, POTMessageMeta};
+use crate::{
+    constants::Constants,
+    diagnostics::{Diagnostic, DiagnosticKind},
+    directives::{self, Directive},
+    eval::eval_string_with,
+    exports::ExportIndex,
+    flags,
+    imports::{CalleeRef, ImportBindings},
+    jsx,
+    keywords::{Keyword, Keywords},
+    pot::{POTMessageID, POTMessageMeta, Reference, SourcePosition},
+    suppressions::{self, Suppressions},
+};
+
+/// How `${}` expressions in tagged templates are represented in the msgid
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum PlaceholderStyle {
+    /// By position, e.g. `Hello ${0}`
+    #[default]
+    Numbered,
+    /// By variable name when the expression is a plain identifier, e.g. `Hello ${name}`.
+    /// Other expressions fall back to their position.
+    Named,
+}
+
+/// Which parts of a reference to the source are written, like xgettext's `--add-location`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum AddLocation {
+    /// The file and line, e.g. `src/app.ts:12`
+    #[default]
+    Full,
+    /// Only the file, e.g. `src/app.ts`
+    File,
+    /// No references
+    Never,
+}
+
+/// Which line a reference points to when a call spans several lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReferenceLine {
+    /// The first line of the call or component
+    #[default]
+    Call,
+    /// The line of the msgid string, or of the text of a component
+    String,
+}
+
+/// Options that control what is extracted from source files
+#[derive(Debug, Default)]
+pub struct ExtractOptions {
+    pub keywords: Keywords,
+    pub placeholders: PlaceholderStyle,
+    /// Also extract comments on the lines before a call, like xgettext's `--add-comments`.
+    /// Only comments starting with this tag are kept, an empty tag keeps all of them.
+    pub add_comments: Option<String>,
+    /// Names of JSX components whose children or `msgid` attribute are extracted, like `Trans`
+    pub jsx_components: Vec<String>,
+    /// When not empty, keywords and components only count when they are imported from one of
+    /// these modules
+    pub import_sources: Vec<String>,
+    /// The string constants exported by project files, to resolve imported constants in arguments
+    pub exports: Option<ExportIndex>,
+    pub add_location: AddLocation,
+    /// Also write the column in references, like `src/app.ts:12:5`
+    pub location_columns: bool,
+    pub reference_line: ReferenceLine,
+}
+
+/// An argument of a keyword call. Tagged templates pass their template after any curried
+/// arguments, so that `` __p('menu')`File` `` is extracted like `__p('menu', 'File')`.
+enum Argument<'a> {
+    Expr(&'a ExprOrSpread),
+    Template(&'a Tpl),
+}
+impl Argument<'_> {
+    fn span(&self) -> Span {
+        match self {
+            Argument::Expr(arg) => arg.expr.span(),
+            Argument::Template(tpl) => tpl.span,
+        }
+    }
+}
+
+/// A message extracted from the arguments of a keyword call
+struct ExtractedMessage {
+    /// The name of the function or component, for diagnostics
+    keyword: String,
+    domain: Option<String>,
+    id: POTMessageID,
+    /// Where the msgid is written, for `--reference-line=string`
+    msgid_span: Span,
+    /// Comments generated while extracting, like the description of placeholders
+    comments: Vec<String>,
+    /// Whether the message comes from a no-op marker like `N_`
+    deferred: bool,
+}
 
 pub struct GettextVisitor<'a> {
     pub pot: Arc<Mutex<crate::pot::POT>>,
     pub cm: Lrc<SourceMap>,
-    pub comments: Option<&'a dyn Comments>,
+    pub comments: Option<&'a SingleThreadedComments>,
     pub references_relative_to: &'a PathBuf,
+    pub options: &'a ExtractOptions,
+    /// The index of the file in the order files are walked, for `--sort-output=source-order`
+    pub file_order: usize,
+    /// Calls that couldn't be extracted, in the order they were found
+    pub diagnostics: Vec<Diagnostic>,
+    /// How many calls were skipped because of `gettext-extractor-disable` comments
+    pub suppressed: usize,
+    /// The line and comments of each statement, declarator, property or JSX expression container
+    /// enclosing the current node, used by `--add-comments` and directive comments
+    comment_anchors: Vec<(usize, Vec<Comment>)>,
+    /// Comments from an empty JSX expression container like `{/* comment */}`, which apply to
+    /// the next JSX expression container
+    pending_jsx_comments: Vec<Comment>,
+    /// Names bound by imports from `--import-source` modules, when that option is used
+    imports: Option<ImportBindings>,
+    /// The `const` bindings in scope, so that arguments can refer to them
+    constants: Constants,
+    /// Where extraction is turned off by comments
+    suppressions: Suppressions,
+}
+impl<'a> GettextVisitor<'a> {
+    pub fn new(
+        pot: Arc<Mutex<crate::pot::POT>>,
+        cm: Lrc<SourceMap>,
+        comments: Option<&'a SingleThreadedComments>,
+        references_relative_to: &'a PathBuf,
+        options: &'a ExtractOptions,
+    ) -> Self {
+        Self {
+            pot,
+            cm,
+            comments,
+            references_relative_to,
+            options,
+            file_order: 0,
+            diagnostics: Vec::new(),
+            suppressed: 0,
+            comment_anchors: Vec::new(),
+            pending_jsx_comments: Vec::new(),
+            imports: None,
+            constants: Constants::default(),
+            suppressions: Suppressions::default(),
+        }
+    }
+
+    /// Finds the keyword a callee refers to. With `--import-source`, only callees bound by
+    /// an import from those modules are keywords.
+    fn resolve_keyword(&self, callee: CalleeRef) -> Option<&'a Keyword> {
+        let name = match &self.imports {
+            Some(imports) => imports.resolve(callee)?,
+            None => callee.name(),
+        };
+        self.options.keywords.get(name)
+    }
+
+    /// Finds the name of the translation component an element refers to, see [Self::resolve_keyword]
+    fn resolve_jsx_component(&self, component: CalleeRef) -> Option<String> {
+        let name = match &self.imports {
+            Some(imports) => imports.resolve(component)?,
+            None => component.name(),
+        };
+        self.options
+            .jsx_components
+            .iter()
+            .find(|component| *component == name)
+            .cloned()
+    }
 }
 impl GettextVisitor<'_> {
-    fn add_message_meta(&self, span: &Span, meta: &mut POTMessageMeta) {
-        if let Some(reference) = format_reference(&self.cm, span, self.references_relative_to) {
-            meta.references.insert(reference);
+    /// Extracts the domain and message from the arguments of a keyword call
+    fn extract_message(
+        &self,
+        keyword: &Keyword,
+        args: &[Argument],
+        span: &Span,
+    ) -> Result<ExtractedMessage, Diagnostic> {
+        let expected = keyword.arity();
+        if args.len() < expected {
+            return Err(Diagnostic::new(
+                &self.cm,
+                *span,
+                &keyword.name,
+                DiagnosticKind::TooFewArguments {
+                    expected,
+                    found: args.len(),
+                },
+            ));
+        }
+
+        let mut comments = Vec::new();
+        let mut extract =
+            |index: usize| self.extract_argument(keyword, &args[index], &mut comments);
+
+        let domain = keyword.domain.map(&mut extract).transpose()?;
+        let id = POTMessageID {
+            msgctx: keyword.context.map(&mut extract).transpose()?,
+            msgid: extract(keyword.msgid)?,
+            msgid_plural: keyword.plural.map(&mut extract).transpose()?,
+        };
+        Ok(ExtractedMessage {
+            keyword: keyword.name.clone(),
+            domain,
+            id,
+            msgid_span: args[keyword.msgid].span(),
+            comments,
+            deferred: keyword.deferred,
+        })
+    }
+
+    fn extract_argument(
+        &self,
+        keyword: &Keyword,
+        arg: &Argument,
+        comments: &mut Vec<String>,
+    ) -> Result<String, Diagnostic> {
+        match arg {
+            Argument::Expr(ExprOrSpread { spread: None, expr }) => {
+                eval_string_with(expr, &self.constants)
+                    .map_err(|error| Diagnostic::from_eval_error(&self.cm, error, &keyword.name))
+            }
+            Argument::Expr(arg) => Err(Diagnostic::new(
+                &self.cm,
+                arg.span(),
+                &keyword.name,
+                DiagnosticKind::NonLiteralArgument,
+            )),
+            Argument::Template(tpl) => {
+                let (value, comment) =
+                    self.extract_template_with_placeholders(tpl, &keyword.name)?;
+                comments.extend(comment);
+                Ok(value)
+            }
+        }
+    }
+
+    /// Extracts the message of a translation component like `<Trans context="menu">Open</Trans>`
+    fn extract_jsx_message(
+        &self,
+        element: &JSXElement,
+        name: &str,
+    ) -> Result<ExtractedMessage, Diagnostic> {
+        let keyword = format!("<{}>", name);
+        let to_diagnostic = |error| Diagnostic::from_eval_error(&self.cm, error, &keyword);
+        let attribute = |name| {
+            jsx::find_attribute(&element.opening, name)
+                .map(|attr| jsx::eval_attribute(attr, &self.constants))
+                .transpose()
+                .map_err(to_diagnostic)
+        };
+
+        let mut comments = Vec::new();
+        let msgid_span = match jsx::find_attribute(&element.opening, "msgid") {
+            Some(attr) => attr.span,
+            None => jsx::children_span(&self.cm, &element.children).unwrap_or(element.span),
+        };
+        let msgid = match attribute("msgid")? {
+            Some(msgid) => msgid,
+            None => {
+                let (msgid, placeholders) =
+                    jsx::serialize_children(&self.cm, &element.children).map_err(to_diagnostic)?;
+                if !placeholders.is_empty() {
+                    comments.push(format!("placeholders: {}", placeholders.join(", ")));
+                }
+                msgid
+            }
+        };
+        if msgid.is_empty() {
+            return Err(Diagnostic::new(
+                &self.cm,
+                element.opening.span,
+                &keyword,
+                DiagnosticKind::EmptyMessage,
+            ));
+        }
+        comments.extend(attribute("comment")?);
+
+        Ok(ExtractedMessage {
+            keyword: keyword.clone(),
+            domain: attribute("domain")?,
+            id: POTMessageID {
+                msgctx: attribute("context")?,
+                msgid,
+                msgid_plural: attribute("plural")?,
+            },
+            msgid_span,
+            comments,
+            deferred: false,
+        })
+    }
+
+    /// Adds an extracted message to the POT, or records why it couldn't be extracted
+    fn add_extracted_message(
+        &mut self,
+        message: Result<ExtractedMessage, Diagnostic>,
+        span: &Span,
+    ) {
+        if self.suppressions.is_suppressed(&self.cm, *span) {
+            self.suppressed += 1;
+            return;
+        }
+        match message {
+            Ok(mut message) => {
+                let flags = self.apply_call_comments(span, &mut message);
+                let reference_span = match self.options.reference_line {
+                    ReferenceLine::Call => *span,
+                    ReferenceLine::String => message.msgid_span,
+                };
+                let pot = &mut self.pot.lock().unwrap();
+                let meta = pot.add_message(message.domain, message.id);
+                meta.deferred |= message.deferred;
+                meta.extracted_comments.extend(message.comments);
+                meta.add_position(SourcePosition {
+                    file: self.file_order,
+                    offset: self.cm.lookup_byte_offset(span.lo).pos.0,
+                });
+                self.add_message_meta(&reference_span, meta, flags)
+            }
+            Err(diagnostic) => self.diagnostics.push(diagnostic),
+        }
+    }
+
+    /// Turns a tagged template into a msgid, replacing each `${}` expression with a placeholder.
+    /// Also returns an extracted comment describing the placeholders, if there are any.
+    fn extract_template_with_placeholders(
+        &self,
+        tpl: &Tpl,
+        keyword: &str,
+    ) -> Result<(String, Option<String>), Diagnostic> {
+        let mut msgid = String::new();
+        let mut placeholders = Vec::<String>::new();
+        for (index, quasi) in tpl.quasis.iter().enumerate() {
+            match &quasi.cooked {
+                Some(cooked) => msgid.push_str(cooked),
+                None => {
+                    return Err(Diagnostic::new(
+                        &self.cm,
+                        quasi.span,
+                        keyword,
+                        DiagnosticKind::NonLiteralArgument,
+                    ))
+                }
+            }
+            if let Some(expr) = tpl.exprs.get(index) {
+                let placeholder = match (self.options.placeholders, expr.deref()) {
+                    (PlaceholderStyle::Named, Expr::Ident(Ident { sym, .. })) => {
+                        format!("${{{}}}", sym)
+                    }
+                    _ => format!("${{{}}}", index),
+                };
+                let source = self
+                    .cm
+                    .span_to_snippet(expr.span())
+                    .map(|source| source.split_whitespace().join(" "))
+                    .unwrap_or_default();
+                placeholders.push(format!("{} = {}", placeholder, source));
+                msgid.push_str(&placeholder);
+            }
+        }
+
+        let comment = if placeholders.is_empty() {
+            None
+        } else {
+            Some(format!("placeholders: {}", placeholders.join(", ")))
+        };
+        Ok((msgid, comment))
+    }
+
+    /// Applies the comments on a call to its message: `xgettext:` and `i18n:` comments set flags
+    /// and the context, and the others are extracted for translators. Returns the flags of the
+    /// message, starting from the formats detected in it.
+    fn apply_call_comments(
+        &mut self,
+        span: &Span,
+        message: &mut ExtractedMessage,
+    ) -> BTreeSet<String> {
+        let mut flags = flags::detect_message(&message.id);
+        for comment in self.call_comments(span) {
+            if let Some(directive) = flags::parse_directive(&comment) {
+                flags::apply_directive(&mut flags, &directive);
+            } else if let Some(options) = directives::parse(&comment) {
+                for option in options {
+                    match option {
+                        Ok(Directive::Context(context)) => message.id.msgctx = Some(context),
+                        Ok(Directive::Flag(flag)) => flags::apply_directive(&mut flags, &[&flag]),
+                        Err(directive) => self.diagnostics.push(Diagnostic::new(
+                            &self.cm,
+                            comment.span,
+                            &message.keyword,
+                            DiagnosticKind::InvalidDirective { directive },
+                        )),
+                    }
+                }
+            } else if !suppressions::is_directive(&comment) {
+                message.comments.push(String::from(comment.text.trim()));
+            }
         }
+        flags
+    }
 
+    /// The comments before or after a call, and the comments on the lines before it that are
+    /// directives or match `--add-comments`
+    fn call_comments(&self, span: &Span) -> Vec<Comment> {
         let mut comments = Vec::<Comment>::new();
 
         if let Some(leading) = self.comments.get_leading(span.lo) {
@@ -35,10 +427,82 @@ impl GettextVisitor<'_> {
             comments.extend(trailing);
         }
 
-        for comment in comments {
-            meta.extracted_comments
-                .insert(String::from(comment.text.trim()));
+        // Only the nodes starting on the same line as the call apply, so that a comment
+        // above a function isn't added to every message inside it
+        let line = self.cm.lookup_char_pos(span.lo).line;
+        for (_, anchor_comments) in self
+            .comment_anchors
+            .iter()
+            .rev()
+            .take_while(|(anchor_line, _)| *anchor_line == line)
+        {
+            comments.extend(
+                anchor_comments
+                    .iter()
+                    .filter(|comment| {
+                        flags::parse_directive(comment).is_some()
+                            || directives::is_directive(comment)
+                            || self
+                                .options
+                                .add_comments
+                                .as_ref()
+                                .is_some_and(|tag| comment.text.trim().starts_with(tag.as_str()))
+                    })
+                    .cloned(),
+            );
+        }
+        comments
+    }
+
+    /// Adds the reference and flags of a call to its message
+    fn add_message_meta(&self, span: &Span, meta: &mut POTMessageMeta, flags: BTreeSet<String>) {
+        if let Some(reference) =
+            format_reference(&self.cm, span, self.references_relative_to, self.options)
+        {
+            meta.references.insert(reference);
+        }
+        for flag in flags {
+            flags::insert(&mut meta.flags, flag);
+        }
+    }
+
+    /// Visits JSX children, passing comments from `{/* comment */}` on to the next expression container
+    fn visit_jsx_children(&mut self, children: &[JSXElementChild]) {
+        for child in children {
+            match child {
+                JSXElementChild::JSXExprContainer(JSXExprContainer {
+                    span,
+                    expr: JSXExpr::JSXEmptyExpr(_),
+                }) => {
+                    self.pending_jsx_comments.extend(
+                        self.comments
+                            .get_trailing(span.lo + BytePos(1))
+                            .unwrap_or_default(),
+                    );
+                }
+                JSXElementChild::JSXText(JSXText { value, .. }) if value.trim().is_empty() => {}
+                JSXElementChild::JSXExprContainer(_) => child.visit_with(self),
+                _ => {
+                    self.pending_jsx_comments.clear();
+                    child.visit_with(self);
+                }
+            }
         }
+        self.pending_jsx_comments.clear();
+    }
+
+    /// Visits a node that translator comments and directives can be attached to
+    fn visit_comment_anchor<N: VisitWith<Self>>(
+        &mut self,
+        node: &N,
+        span: Span,
+        mut comments: Vec<Comment>,
+    ) {
+        comments.extend(self.comments.get_leading(span.lo).unwrap_or_default());
+        self.comment_anchors
+            .push((self.cm.lookup_char_pos(span.lo).line, comments));
+        node.visit_children_with(self);
+        self.comment_anchors.pop();
     }
 }
 impl Visit for GettextVisitor<'_> {
@@ -53,216 +517,214 @@ impl Visit for GettextVisitor<'_> {
             ..
         } = &call
         {
-            let name = match &expr.deref() {
-                // object.method()
-                Expr::Member(MemberExpr {
-                    prop: MemberProp::Ident(Ident { sym, .. }),
-                    ..
-                }) => Some(sym.as_str()),
-                // method()
-                Expr::Ident(Ident { sym, .. }) => Some(sym.as_str()),
-                _ => None,
-            };
-
-            if let Some(sym) = name {
-                match sym {
-                    "__" | "gettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }] = &args[..1] {
-                            if let (Some(value1),) = (&extract_string_from_expr(expr1),) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    None,
-                                    POTMessageID {
-                                        msgid: value1.to_string(),
-                                        ..Default::default()
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    "__n" | "ngettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }, ExprOrSpread { expr: expr2, .. }] =
-                            &args[..2]
-                        {
-                            if let (Some(value1), Some(value2)) = (
-                                &extract_string_from_expr(expr1),
-                                &extract_string_from_expr(expr2),
-                            ) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    None,
-                                    POTMessageID {
-                                        msgid: value1.to_string(),
-                                        msgid_plural: Some(value2.to_string()),
-                                        ..Default::default()
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    "__p" | "pgettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }, ExprOrSpread { expr: expr2, .. }] =
-                            &args[..2]
-                        {
-                            if let (Some(value1), Some(value2)) = (
-                                &extract_string_from_expr(expr1),
-                                &extract_string_from_expr(expr2),
-                            ) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    None,
-                                    POTMessageID {
-                                        msgctx: Some(value1.to_string()),
-                                        msgid: value2.to_string(),
-                                        ..Default::default()
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    "__np" | "npgettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }, ExprOrSpread { expr: expr2, .. }, ExprOrSpread { expr: expr3, .. }] =
-                            &args[..3]
-                        {
-                            if let (Some(value1), Some(value2), Some(value3)) = (
-                                &extract_string_from_expr(expr1),
-                                &extract_string_from_expr(expr2),
-                                &extract_string_from_expr(expr3),
-                            ) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    None,
-                                    POTMessageID {
-                                        msgctx: Some(value1.to_string()),
-                                        msgid: value2.to_string(),
-                                        msgid_plural: Some(value3.to_string()),
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    "__d" | "dgettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }, ExprOrSpread { expr: expr2, .. }] =
-                            &args[..2]
-                        {
-                            if let (Some(value1), Some(value2)) = (
-                                &extract_string_from_expr(expr1),
-                                &extract_string_from_expr(expr2),
-                            ) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    Some(value1.to_string()),
-                                    POTMessageID {
-                                        msgid: value2.to_string(),
-                                        ..Default::default()
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    "__dn" | "dngettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }, ExprOrSpread { expr: expr2, .. }, ExprOrSpread { expr: expr3, .. }] =
-                            &args[..3]
-                        {
-                            if let (Some(value1), Some(value2), Some(value3)) = (
-                                &extract_string_from_expr(expr1),
-                                &extract_string_from_expr(expr2),
-                                &extract_string_from_expr(expr3),
-                            ) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    Some(value1.to_string()),
-                                    POTMessageID {
-                                        msgid: value2.to_string(),
-                                        msgid_plural: Some(value3.to_string()),
-                                        ..Default::default()
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    "__dp" | "dpgettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }, ExprOrSpread { expr: expr2, .. }, ExprOrSpread { expr: expr3, .. }] =
-                            &args[..3]
-                        {
-                            if let (Some(value1), Some(value2), Some(value3)) = (
-                                &extract_string_from_expr(expr1),
-                                &extract_string_from_expr(expr2),
-                                &extract_string_from_expr(expr3),
-                            ) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    Some(value1.to_string()),
-                                    POTMessageID {
-                                        msgctx: Some(value2.to_string()),
-                                        msgid: value3.to_string(),
-                                        ..Default::default()
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    "__dnp" | "dnpgettext" => {
-                        if let [ExprOrSpread { expr: expr1, .. }, ExprOrSpread { expr: expr2, .. }, ExprOrSpread { expr: expr3, .. }, ExprOrSpread { expr: expr4, .. }] =
-                            &args[..4]
-                        {
-                            if let (Some(value1), Some(value2), Some(value3), Some(value4)) = (
-                                &extract_string_from_expr(expr1),
-                                &extract_string_from_expr(expr2),
-                                &extract_string_from_expr(expr3),
-                                &extract_string_from_expr(expr4),
-                            ) {
-                                let pot = &mut self.pot.lock().unwrap();
-                                let meta = pot.add_message(
-                                    Some(value1.to_string()),
-                                    POTMessageID {
-                                        msgctx: Some(value2.to_string()),
-                                        msgid: value3.to_string(),
-                                        msgid_plural: Some(value4.to_string()),
-                                    },
-                                );
-                                self.add_message_meta(span, meta)
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+            if let Some(keyword) = callee_ref(expr).and_then(|callee| self.resolve_keyword(callee))
+            {
+                let args = args.iter().map(Argument::Expr).collect::<Vec<_>>();
+                self.add_extracted_message(self.extract_message(keyword, &args, span), span);
             }
         }
     }
 
-    fn visit_tagged_tpl(&mut self, n: &TaggedTpl) {
+    fn visit_opt_chain_expr(&mut self, n: &OptChainExpr) {
         n.visit_children_with(self);
+        // __?.('x') and i18n?.__('x') are optional calls rather than call expressions
+        if let OptChainBase::Call(OptCall { callee, args, .. }) = n.base.as_ref() {
+            if let Some(keyword) =
+                callee_ref(callee).and_then(|callee| self.resolve_keyword(callee))
+            {
+                let args = args.iter().map(Argument::Expr).collect::<Vec<_>>();
+                self.add_extracted_message(self.extract_message(keyword, &args, &n.span), &n.span);
+            }
+        }
+    }
 
+    fn visit_tagged_tpl(&mut self, n: &TaggedTpl) {
         let TaggedTpl { span, tag, tpl, .. } = n;
 
-        if let Expr::Ident(Ident { sym, .. }) = tag.deref() {
-            let Tpl { quasis, .. } = tpl.deref();
-            if *sym == "__" {
-                if let [TplElement {
-                    cooked: Some(value),
-                    ..
-                }] = &quasis[..1]
-                {
-                    let pot = &mut self.pot.lock().unwrap();
-                    let meta = pot.add_message(
-                        None,
-                        POTMessageID {
-                            msgid: value.to_string(),
-                            ..Default::default()
-                        },
-                    );
-                    self.add_message_meta(span, meta)
+        // Curried tags like __p('menu')`File` pass their arguments before the template
+        let mut callee = tag.deref();
+        let mut curried_args = Vec::<&ExprOrSpread>::new();
+        while let Expr::Call(CallExpr {
+            callee: Callee::Expr(inner),
+            args,
+            ..
+        }) = callee
+        {
+            curried_args.splice(0..0, args);
+            callee = inner;
+        }
+
+        match callee_ref(callee).and_then(|callee| self.resolve_keyword(callee)) {
+            Some(keyword) => {
+                // Curried calls aren't complete keyword calls by themselves, so only their parts are visited
+                callee.visit_with(self);
+                for arg in &curried_args {
+                    arg.visit_with(self);
                 }
+                tpl.visit_with(self);
+
+                let args = curried_args
+                    .into_iter()
+                    .map(Argument::Expr)
+                    .chain([Argument::Template(tpl)])
+                    .collect::<Vec<_>>();
+                // The template must be the last argument the keyword refers to
+                let message = match args.len() > keyword.arity() {
+                    true => Err(Diagnostic::new(
+                        &self.cm,
+                        *span,
+                        &keyword.name,
+                        DiagnosticKind::TooManyArguments {
+                            expected: keyword.arity(),
+                            found: args.len(),
+                        },
+                    )),
+                    false => self.extract_message(keyword, &args, span),
+                };
+                self.add_extracted_message(message, span);
             }
+            None => n.visit_children_with(self),
+        }
+    }
+
+    fn visit_module(&mut self, n: &Module) {
+        if !self.options.import_sources.is_empty() {
+            self.imports = Some(ImportBindings::collect(n, &self.options.import_sources));
+        }
+        let path = match self.cm.span_to_filename(n.span) {
+            FileName::Real(path) if !n.span.is_dummy() => Some(path),
+            _ => None,
+        };
+        let exports = self.options.exports.as_ref().zip(path.as_deref());
+        self.constants.declare_module(n, exports);
+        if let Some(comments) = self.comments {
+            self.suppressions = Suppressions::collect(&self.cm, comments);
+        }
+        n.visit_children_with(self);
+    }
+
+    fn visit_block_stmt(&mut self, n: &BlockStmt) {
+        self.constants.enter_scope();
+        self.constants.declare_stmts(&n.stmts);
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_function(&mut self, n: &Function) {
+        self.constants.enter_scope();
+        for param in &n.params {
+            self.constants.declare_pat(&param.pat);
+        }
+        if let Some(body) = &n.body {
+            self.constants.declare_vars(body);
+        }
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_arrow_expr(&mut self, n: &ArrowExpr) {
+        self.constants.enter_scope();
+        for param in &n.params {
+            self.constants.declare_pat(param);
+        }
+        self.constants.declare_vars(n.body.as_ref());
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_catch_clause(&mut self, n: &CatchClause) {
+        self.constants.enter_scope();
+        if let Some(param) = &n.param {
+            self.constants.declare_pat(param);
+        }
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_for_stmt(&mut self, n: &ForStmt) {
+        self.constants.enter_scope();
+        if let Some(VarDeclOrExpr::VarDecl(decl)) = &n.init {
+            self.constants.declare_var_decl(decl);
+        }
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_for_in_stmt(&mut self, n: &ForInStmt) {
+        self.constants.enter_scope();
+        if let ForHead::VarDecl(decl) = &n.left {
+            self.constants.declare_var_decl(decl);
+        }
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_for_of_stmt(&mut self, n: &ForOfStmt) {
+        self.constants.enter_scope();
+        if let ForHead::VarDecl(decl) = &n.left {
+            self.constants.declare_var_decl(decl);
+        }
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_switch_stmt(&mut self, n: &SwitchStmt) {
+        self.constants.enter_scope();
+        self.constants
+            .declare_stmts(n.cases.iter().flat_map(|case| &case.cons));
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_ts_module_block(&mut self, n: &TsModuleBlock) {
+        self.constants.enter_scope();
+        self.constants.declare_vars(n);
+        self.constants.declare_module_items(&n.body, None);
+        n.visit_children_with(self);
+        self.constants.leave_scope();
+    }
+
+    fn visit_stmt(&mut self, n: &Stmt) {
+        self.visit_comment_anchor(n, n.span(), Vec::new());
+    }
+
+    fn visit_module_decl(&mut self, n: &ModuleDecl) {
+        self.visit_comment_anchor(n, n.span(), Vec::new());
+    }
+
+    fn visit_var_declarator(&mut self, n: &VarDeclarator) {
+        self.visit_comment_anchor(n, n.span, Vec::new());
+    }
+
+    fn visit_prop(&mut self, n: &Prop) {
+        self.visit_comment_anchor(n, n.span(), Vec::new());
+    }
+
+    fn visit_jsx_expr_container(&mut self, n: &JSXExprContainer) {
+        // Comments at the start of a container are attached after its opening brace
+        let mut comments = std::mem::take(&mut self.pending_jsx_comments);
+        comments.extend(
+            self.comments
+                .get_trailing(n.span.lo + BytePos(1))
+                .unwrap_or_default(),
+        );
+        self.visit_comment_anchor(n, n.span, comments);
+    }
+
+    fn visit_jsx_element(&mut self, n: &JSXElement) {
+        if let Some(name) = jsx::element_ref(&n.opening.name)
+            .and_then(|component| self.resolve_jsx_component(component))
+        {
+            self.add_extracted_message(self.extract_jsx_message(n, &name), &n.span);
         }
+
+        n.opening.visit_with(self);
+        self.visit_jsx_children(&n.children);
+        n.closing.visit_with(self);
+    }
+
+    fn visit_jsx_fragment(&mut self, n: &JSXFragment) {
+        self.visit_jsx_children(&n.children);
     }
 
     fn visit_decorator(&mut self, n: &Decorator) {
@@ -272,32 +734,67 @@ impl Visit for GettextVisitor<'_> {
     }
 }
 
+/// How the function being called is referenced, for both `method()` and `object.method()`
+fn callee_ref(callee: &Expr) -> Option<CalleeRef<'_>> {
+    match callee {
+        // object.method(), object["method"]()
+        Expr::Member(member) => member_ref(member),
+        // object?.method()
+        Expr::OptChain(OptChainExpr { base, .. }) => match base.as_ref() {
+            OptChainBase::Member(member) => member_ref(member),
+            OptChainBase::Call(_) => None,
+        },
+        // method()
+        Expr::Ident(Ident { sym, .. }) => Some(CalleeRef::Ident(sym)),
+        // (method)(), (object.method)()
+        Expr::Paren(ParenExpr { expr, .. }) => callee_ref(expr),
+        _ => None,
+    }
+}
+
+/// Only identifiers, string literals and templates without substitutions are resolved as property names, as
+/// `object[method]` could be anything
+fn member_ref(member: &MemberExpr) -> Option<CalleeRef<'_>> {
+    let name = match &member.prop {
+        MemberProp::Ident(Ident { sym, .. }) => sym,
+        MemberProp::Computed(ComputedPropName { expr, .. }) => match expr.as_ref() {
+            Expr::Lit(Lit::Str(Str { value, .. })) => value,
+            Expr::Tpl(Tpl { exprs, quasis, .. }) if exprs.is_empty() => {
+                quasis.first()?.cooked.as_ref()?
+            }
+            _ => return None,
+        },
+        MemberProp::PrivateName(_) => return None,
+    };
+    let object = match member.obj.as_ref() {
+        Expr::Ident(Ident { sym, .. }) => Some(sym.as_ref()),
+        _ => None,
+    };
+    Some(CalleeRef::Member(object, name))
+}
+
 fn format_reference(
     cm: &Lrc<SourceMap>,
     span: &Span,
     references_relative_to: &PathBuf,
-) -> Option<String> {
+    options: &ExtractOptions,
+) -> Option<Reference> {
+    if options.add_location == AddLocation::Never {
+        return None;
+    }
     let loc = cm.lookup_char_pos(span.lo);
     let file = match pathdiff::diff_paths(loc.file.name.to_string(), references_relative_to) {
         Some(relative) => relative.into_os_string().into_string(),
         None => Ok(loc.file.name.to_string()),
-    };
-
-    match file {
-        Ok(file) => Some(format!("{}:{}", file, loc.line)),
-        Err(_) => None,
     }
-}
+    .ok()?;
 
-fn extract_string_from_expr(expr: &Expr) -> Option<String> {
-    match expr {
-        Expr::Lit(Lit::Str(Str { value, .. })) => Some(value.to_string()),
-        Expr::Tpl(Tpl { quasis, .. }) => match &quasis[..] {
-            [TplElement { cooked, .. }] => cooked.as_ref().map(|s| s.to_string()),
-            _ => None,
-        },
-        _ => None,
-    }
+    Some(match (options.add_location, options.location_columns) {
+        (AddLocation::File, _) => Reference::new(&file, None, None),
+        // One-based, like diagnostics
+        (_, true) => Reference::new(&file, Some(loc.line), Some(loc.col_display + 1)),
+        (_, false) => Reference::new(&file, Some(loc.line), None),
+    })
 }
 
 #[cfg(test)]
@@ -316,7 +813,9 @@ mod tests {
             pot.lock().unwrap().to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: test.js:1
@@ -342,7 +841,9 @@ context.i18n[__]("Something else");
             pot.lock().unwrap().to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: test.js:2
@@ -359,19 +860,47 @@ msgstr[1] ""
     }
 
     #[test]
-    fn detects_plural_message_with_no_context() {
+    fn detects_optional_calls() {
         let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
-        parse("test.js", r#"__n("1 file", "%d files");"#, Arc::clone(&pot));
+        parse(
+            "test.js",
+            r#"
+i18n?.__("Optional member");
+__?.("Optional call");
+i18n?.__?.("Optional member call");
+window.i18n?.__n("Optional plural", "Optional plurals", count);
+i18n?.["__"]("Optional computed member");
+"#,
+            Arc::clone(&pot),
+        );
         assert_eq!(
             pot.lock().unwrap().to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
-#: test.js:1
-msgid "1 file"
-msgid_plural "%d files"
+#: test.js:3
+msgid "Optional call"
+msgstr ""
+
+#: test.js:6
+msgid "Optional computed member"
+msgstr ""
+
+#: test.js:2
+msgid "Optional member"
+msgstr ""
+
+#: test.js:4
+msgid "Optional member call"
+msgstr ""
+
+#: test.js:5
+msgid "Optional plural"
+msgid_plural "Optional plurals"
 msgstr[0] ""
 msgstr[1] ""
 "#
@@ -379,77 +908,278 @@ msgstr[1] ""
     }
 
     #[test]
-    fn detects_singular_message_with_context() {
+    fn detects_string_literal_computed_members() {
         let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
         parse(
             "test.js",
-            r#"__p("menu", "Hello, world!");"#,
+            r#"
+i18n["__"]("Double quotes");
+i18n['__p']("menu", "Single quotes");
+context.i18n["__"]("Nested object");
+i18n[`__`]("Template literal");
+i18n[`${method}`]("Template with substitutions");
+i18n[method]("Not a string literal");
+"#,
             Arc::clone(&pot),
         );
         assert_eq!(
             pot.lock().unwrap().to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
-#: test.js:1
+#: test.js:2
+msgid "Double quotes"
+msgstr ""
+
+#: test.js:4
+msgid "Nested object"
+msgstr ""
+
+#: test.js:5
+msgid "Template literal"
+msgstr ""
+
+#: test.js:3
 msgctxt "menu"
-msgid "Hello, world!"
+msgid "Single quotes"
 msgstr ""
 "#
         );
     }
 
     #[test]
-    fn detects_plural_message_with_context() {
+    fn detects_parenthesised_callees() {
         let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
         parse(
             "test.js",
-            r#"__np("menu", "1 file", "%d files");"#,
+            r#"
+(__)("Parenthesised function");
+(i18n.__)("Parenthesised method");
+((i18n?.__))?.("Parenthesised optional method");
+"#,
             Arc::clone(&pot),
         );
         assert_eq!(
             pot.lock().unwrap().to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
-#: test.js:1
-msgctxt "menu"
-msgid "1 file"
-msgid_plural "%d files"
-msgstr[0] ""
-msgstr[1] ""
+#: test.js:2
+msgid "Parenthesised function"
+msgstr ""
+
+#: test.js:3
+msgid "Parenthesised method"
+msgstr ""
+
+#: test.js:4
+msgid "Parenthesised optional method"
+msgstr ""
 "#
         );
     }
 
     #[test]
-    fn detects_message_with_comment() {
+    fn detects_noop_markers_as_deferred() {
         let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
         parse(
             "test.js",
-            r#"/* Test comment */ __("Test message");"#,
+            r#"
+const STATUS_LABELS = {
+    open: N_('Open'),
+    closed: gettext_noop('Closed'),
+    archived: pgettext_noop('status', 'Archived'),
+    pending: dgettext_noop('billing', 'Pending'),
+};
+const COUNTS = [ngettext_noop('1 item', '%d items'), dngettext_noop('billing', '1 invoice', '%d invoices')];
+__(label);
+__('Closed');
+"#,
             Arc::clone(&pot),
         );
+        let pot = pot.lock().unwrap();
+        let deferred =
+            |domain: &str, msgctx: Option<&str>, msgid: &str, msgid_plural: Option<&str>| {
+                pot.domains[domain]
+                    .get(&POTMessageID {
+                        msgctx: msgctx.map(String::from),
+                        msgid: msgid.to_string(),
+                        msgid_plural: msgid_plural.map(String::from),
+                    })
+                    .unwrap_or_else(|| panic!("Missing {}", msgid))
+                    .deferred
+            };
+        assert!(deferred("default", None, "Open", None));
+        assert!(deferred("default", Some("status"), "Archived", None));
+        assert!(deferred("billing", None, "Pending", None));
+        assert!(deferred("default", None, "1 item", Some("%d items")));
+        assert!(deferred("billing", None, "1 invoice", Some("%d invoices")));
+        // Still deferred when it's also translated directly
+        assert!(deferred("default", None, "Closed", None));
+
         assert_eq!(
-            pot.lock().unwrap().to_string(None).unwrap(),
+            pot.to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
-#. Test comment
-#: test.js:1
-msgid "Test message"
+#. Marked for deferred translation
+#: test.js:8
+#, c-format
+msgid "1 item"
+msgid_plural "%d items"
+msgstr[0] ""
+msgstr[1] ""
+
+#. Marked for deferred translation
+#: test.js:4 test.js:10
+msgid "Closed"
 msgstr ""
-"#
-        );
-    }
 
-    // Comments seem to behave differently in tsx, so this is a regression test
+#. Marked for deferred translation
+#: test.js:3
+msgid "Open"
+msgstr ""
+
+#. Marked for deferred translation
+#: test.js:5
+msgctxt "status"
+msgid "Archived"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn regular_calls_are_not_deferred() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse("test.js", "__('Open');", Arc::clone(&pot));
+        let pot = pot.lock().unwrap();
+        assert!(
+            !pot.domains["default"]
+                .get(&POTMessageID {
+                    msgctx: None,
+                    msgid: "Open".to_string(),
+                    msgid_plural: None,
+                })
+                .unwrap()
+                .deferred
+        );
+    }
+
+    #[test]
+    fn detects_plural_message_with_no_context() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse("test.js", r#"__n("1 file", "%d files");"#, Arc::clone(&pot));
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.js:1
+#, c-format
+msgid "1 file"
+msgid_plural "%d files"
+msgstr[0] ""
+msgstr[1] ""
+"#
+        );
+    }
+
+    #[test]
+    fn detects_singular_message_with_context() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "test.js",
+            r#"__p("menu", "Hello, world!");"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.js:1
+msgctxt "menu"
+msgid "Hello, world!"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn detects_plural_message_with_context() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "test.js",
+            r#"__np("menu", "1 file", "%d files");"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.js:1
+#, c-format
+msgctxt "menu"
+msgid "1 file"
+msgid_plural "%d files"
+msgstr[0] ""
+msgstr[1] ""
+"#
+        );
+    }
+
+    #[test]
+    fn detects_message_with_comment() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "test.js",
+            r#"/* Test comment */ __("Test message");"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. Test comment
+#: test.js:1
+msgid "Test message"
+msgstr ""
+"#
+        );
+    }
+
+    // Comments seem to behave differently in tsx, so this is a regression test
     #[test]
     fn detects_message_with_comment_in_jsx() {
         let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
@@ -465,11 +1195,14 @@ msgstr ""
             pot.lock().unwrap().to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #. Test comment
 #: file.tsx:3
+#, c-format
 msgid "1 object"
 msgid_plural "%d objects"
 msgstr[0] ""
@@ -478,6 +1211,267 @@ msgstr[1] ""
         );
     }
 
+    #[test]
+    fn extracts_translator_comments_from_previous_lines() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse_with_options(
+            "file.tsx",
+            r#"
+// TRANSLATORS: shown on the checkout button
+const pay = __("Pay");
+
+// Not for translators
+export const cancel = __("Cancel");
+
+const labels = {
+    // TRANSLATORS: a menu item
+    open: __("Open"),
+};
+
+// TRANSLATORS: this describes the function, not the string
+function render() {
+    return <p>
+        {/* TRANSLATORS: a paragraph */}
+        {__("Paragraph")}
+        <b title={/* TRANSLATORS: a title */ __("Title")} />
+    </p>;
+}
+"#,
+            Arc::clone(&pot),
+            &ExtractOptions {
+                add_comments: Some("TRANSLATORS:".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: file.tsx:6
+msgid "Cancel"
+msgstr ""
+
+#. TRANSLATORS: a menu item
+#: file.tsx:10
+msgid "Open"
+msgstr ""
+
+#. TRANSLATORS: a paragraph
+#: file.tsx:17
+msgid "Paragraph"
+msgstr ""
+
+#. TRANSLATORS: shown on the checkout button
+#: file.tsx:3
+msgid "Pay"
+msgstr ""
+
+#. TRANSLATORS: a title
+#: file.tsx:18
+msgid "Title"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn extracts_all_comments_from_previous_lines_without_a_tag() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse_with_options(
+            "test.js",
+            r#"
+// Shown on the checkout button
+const pay = __("Pay");
+"#,
+            Arc::clone(&pot),
+            &ExtractOptions {
+                add_comments: Some("".to_string()),
+                ..Default::default()
+            },
+        );
+        assert!(pot
+            .lock()
+            .unwrap()
+            .to_string(None)
+            .unwrap()
+            .contains("#. Shown on the checkout button\n"));
+
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "test.js",
+            r#"
+// Shown on the checkout button
+const pay = __("Pay");
+"#,
+            Arc::clone(&pot),
+        );
+        assert!(!pot.lock().unwrap().to_string(None).unwrap().contains("#."));
+    }
+
+    #[test]
+    fn detects_jsx_translation_components() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let diagnostics = parse_with_options(
+            "file.tsx",
+            r#"
+<>
+    <Trans context="menu">Open <b>file</b></Trans>
+    <T msgid="Save" plural="Saves" comment="Toolbar button" />
+    <i18n.Trans domain={'checkout'}>
+        Hello {user.name}, you have {count}{" "}
+        <Link to="/cart">items <Icon /></Link> in {getCart().name}
+    </i18n.Trans>
+    <Trans context={context}>Dynamic</Trans>
+    <Trans />
+    <Other>Not extracted</Other>
+</>;
+"#,
+            Arc::clone(&pot),
+            &ExtractOptions {
+                jsx_components: vec!["Trans".to_string(), "T".to_string()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "file.tsx:9:21: Skipping `<Trans>` call: argument is not a string literal",
+                "file.tsx:10:5: Skipping `<Trans>` call: message is empty",
+            ]
+        );
+        let pot = pot.lock().unwrap();
+        assert_eq!(
+            pot.to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. Toolbar button
+#: file.tsx:4
+msgid "Save"
+msgid_plural "Saves"
+msgstr[0] ""
+msgstr[1] ""
+
+#: file.tsx:3
+msgctxt "menu"
+msgid "Open <0>file</0>"
+msgstr ""
+"#
+        );
+        assert_eq!(
+            pot.to_string(Some("checkout")).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. placeholders: {name} = user.name, {count} = count, {0} = getCart().name
+#: file.tsx:5
+#, python-brace-format
+msgid "Hello {name}, you have {count} <0>items <1/></0> in {0}"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn names_jsx_placeholders_after_variables_and_properties() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse_with_options(
+            "file.tsx",
+            "<Trans>{a.b} {count} {c.d} {count} {user.name} {name} {f()}</Trans>;",
+            Arc::clone(&pot),
+            &ExtractOptions {
+                jsx_components: vec!["Trans".to_string()],
+                ..Default::default()
+            },
+        );
+        let pot = pot.lock().unwrap().to_string(None).unwrap();
+        assert!(pot.contains(
+            "#. placeholders: {b} = a.b, {count} = count, {d} = c.d, {name} = user.name, {0}\n"
+        ));
+        assert!(pot.contains("msgid \"{b} {count} {d} {count} {name} {0} {1}\"\n"));
+    }
+
+    #[test]
+    fn only_detects_imported_keywords_with_import_sources() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse_with_options(
+            "file.tsx",
+            r#"
+import { gettext as t, __p } from '@our/i18n';
+import * as i18n from '@our/i18n';
+import lodash, { __ } from 'lodash';
+const { ngettext: tn, __d } = require('@our/i18n');
+const translations = require('@our/i18n');
+const tdp = require('@our/i18n').dpgettext;
+import { Trans } from '@our/i18n';
+
+t('Renamed import');
+__p('menu', 'Named import');
+i18n.gettext('Namespace import');
+tn('1 file', '%d files', count);
+__d('billing', 'Destructured require');
+translations.__('Required module');
+tdp('billing', 'menu', 'Required member');
+i18n.__`Namespace tag`;
+<Trans>Imported component</Trans>;
+
+__(lodash, 'Not bound to the configured module');
+gettext('Not imported');
+lodash.__('Not the configured module');
+other.gettext('Not a namespace');
+"#,
+            Arc::clone(&pot),
+            &ExtractOptions {
+                import_sources: vec!["@our/i18n".to_string()],
+                jsx_components: vec!["Trans".to_string()],
+                ..Default::default()
+            },
+        );
+        let pot = pot.lock().unwrap();
+        let msgids = pot
+            .domains
+            .values()
+            .flat_map(|file| {
+                file.convert_to_string(&Default::default(), &Default::default())
+                    .lines()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|line| line.starts_with("msgid \"") && line != "msgid \"\"")
+            .sorted()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            msgids,
+            vec![
+                "msgid \"1 file\"",
+                "msgid \"Destructured require\"",
+                "msgid \"Imported component\"",
+                "msgid \"Named import\"",
+                "msgid \"Namespace import\"",
+                "msgid \"Namespace tag\"",
+                "msgid \"Renamed import\"",
+                "msgid \"Required member\"",
+                "msgid \"Required module\"",
+            ]
+        );
+    }
+
     #[test]
     fn detects_tagged_template_literal() {
         let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
@@ -486,7 +1480,9 @@ msgstr[1] ""
             pot.lock().unwrap().to_string(None).unwrap(),
             r#"msgid ""
 msgstr ""
+"MIME-Version: 1.0\n"
 "Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
 "Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
 #: tpl.js:1
@@ -497,40 +1493,882 @@ msgstr ""
     }
 
     #[test]
-    fn detects_decorator_with_gettext() {
+    fn detects_tagged_template_with_numbered_placeholders() {
         let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
         parse(
-            "decorator.ts",
-            r#"
-@Component({
-    title: __("My Component")
-})
-class MyComponent {}
-
-@Get(__("API endpoint"))
-class MyController {}
-"#,
+            "tpl.js",
+            r#"__`Hello ${user.name}, you have ${count} items`;"#,
             Arc::clone(&pot),
         );
-        let output = pot.lock().unwrap().to_string(None).unwrap();
-        // Check that both strings are present in the output
-        assert!(output.contains("msgid \"My Component\""));
-        assert!(output.contains("msgid \"API endpoint\""));
-        assert!(output.contains("#: decorator.ts:3"));
-        assert!(output.contains("#: decorator.ts:7"));
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. placeholders: ${0} = user.name, ${1} = count
+#: tpl.js:1
+#, javascript-format
+msgid "Hello ${0}, you have ${1} items"
+msgstr ""
+"#
+        );
     }
 
-    use swc_ecma_visit::VisitWith;
+    #[test]
+    fn detects_tagged_template_with_named_placeholders() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse_with_options(
+            "tpl.js",
+            r#"__`Hello ${user.name}, you have ${count} items`;"#,
+            Arc::clone(&pot),
+            &ExtractOptions {
+                placeholders: PlaceholderStyle::Named,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
 
-    fn parse(filename: &str, source: &str, pot: Arc<Mutex<crate::pot::POT>>) {
-        let cm: Lrc<SourceMap> = Default::default();
-        let comments: swc_common::comments::SingleThreadedComments = Default::default();
-        let mut visitor = GettextVisitor {
-            pot,
-            cm: Lrc::clone(&cm),
-            comments: Some(&comments),
-            references_relative_to: &PathBuf::from("."),
-        };
+#. placeholders: ${0} = user.name, ${count} = count
+#: tpl.js:1
+#, javascript-format
+msgid "Hello ${0}, you have ${count} items"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn detects_tagged_templates_for_every_keyword_family() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let diagnostics = parse(
+            "tpl.js",
+            r#"
+i18n.__`Save`;
+__p('menu')`File`;
+i18n.__np('menu')('1 file')`${count} files`;
+__d('checkout')`Pay now`;
+__dp('checkout', 'button')`Pay ${amount}`;
+__n`1 file`;
+styled('div')`color: red;`;
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            vec!["tpl.js:7:1: Skipping `__n` call: expected 2 arguments, found 1"]
+        );
+        let pot = pot.lock().unwrap();
+        assert_eq!(
+            pot.to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: tpl.js:2
+msgid "Save"
+msgstr ""
+
+#. placeholders: ${0} = count
+#: tpl.js:4
+#, javascript-format
+msgctxt "menu"
+msgid "1 file"
+msgid_plural "${0} files"
+msgstr[0] ""
+msgstr[1] ""
+
+#: tpl.js:3
+msgctxt "menu"
+msgid "File"
+msgstr ""
+"#
+        );
+        assert_eq!(
+            pot.to_string(Some("checkout")).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: tpl.js:5
+msgid "Pay now"
+msgstr ""
+
+#. placeholders: ${0} = amount
+#: tpl.js:6
+#, javascript-format
+msgctxt "button"
+msgid "Pay ${0}"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn detects_curried_plural_tagged_templates() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "tpl.js",
+            r#"__n('1 file')`${count} files`;"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. placeholders: ${0} = count
+#: tpl.js:1
+#, javascript-format
+msgid "1 file"
+msgid_plural "${0} files"
+msgstr[0] ""
+msgstr[1] ""
+"#
+        );
+    }
+
+    #[test]
+    fn reports_curried_tagged_templates_with_extra_arguments() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let diagnostics = parse(
+            "tpl.js",
+            r#"
+__('x')`y`;
+__p('menu', 'File')`Other`;
+__p('menu')`File`;
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "tpl.js:2:1: Skipping `__` call: expected 1 arguments including the template, found 2",
+                "tpl.js:3:1: Skipping `__p` call: expected 2 arguments including the template, found 3",
+            ]
+        );
+        let output = pot.lock().unwrap().to_string(None).unwrap();
+        assert!(output.contains("msgid \"File\""));
+        assert!(!output.contains("msgid \"x\""));
+        assert!(!output.contains("msgid \"Other\""));
+    }
+
+    #[test]
+    fn detects_decorator_with_gettext() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "decorator.ts",
+            r#"
+@Component({
+    title: __("My Component")
+})
+class MyComponent {}
+
+@Get(__("API endpoint"))
+class MyController {}
+"#,
+            Arc::clone(&pot),
+        );
+        let output = pot.lock().unwrap().to_string(None).unwrap();
+        // Check that both strings are present in the output
+        assert!(output.contains("msgid \"My Component\""));
+        assert!(output.contains("msgid \"API endpoint\""));
+        assert!(output.contains("#: decorator.ts:3"));
+        assert!(output.contains("#: decorator.ts:7"));
+    }
+
+    #[test]
+    fn formats_references_with_options() {
+        let source = r#"
+@Component({
+    title: __(
+        "My Component"
+    )
+})
+class MyComponent {}
+
+const text = <Trans>
+    Hello <b>world</b>
+</Trans>;
+const attribute = <Trans
+    msgid="Attribute"
+/>;
+"#;
+        // The references of "Attribute", "Hello <0>world</0>" and "My Component"
+        let references = |options: ExtractOptions| {
+            let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+            parse_with_options(
+                "decorator.tsx",
+                source,
+                Arc::clone(&pot),
+                &ExtractOptions {
+                    jsx_components: vec!["Trans".to_string()],
+                    ..options
+                },
+            );
+            let output = pot.lock().unwrap().to_string(None).unwrap();
+            output
+                .lines()
+                .filter(|line| line.starts_with("#:"))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            references(ExtractOptions::default()),
+            vec![
+                "#: decorator.tsx:12",
+                "#: decorator.tsx:9",
+                "#: decorator.tsx:3"
+            ]
+        );
+        assert_eq!(
+            references(ExtractOptions {
+                location_columns: true,
+                ..Default::default()
+            }),
+            vec![
+                "#: decorator.tsx:12:19",
+                "#: decorator.tsx:9:14",
+                "#: decorator.tsx:3:12",
+            ]
+        );
+        assert_eq!(
+            references(ExtractOptions {
+                location_columns: true,
+                reference_line: ReferenceLine::String,
+                ..Default::default()
+            }),
+            vec![
+                "#: decorator.tsx:13:5",
+                "#: decorator.tsx:10:5",
+                "#: decorator.tsx:4:9",
+            ]
+        );
+        assert_eq!(
+            references(ExtractOptions {
+                add_location: AddLocation::File,
+                location_columns: true,
+                ..Default::default()
+            }),
+            vec!["#: decorator.tsx", "#: decorator.tsx", "#: decorator.tsx"]
+        );
+        assert_eq!(
+            references(ExtractOptions {
+                add_location: AddLocation::Never,
+                ..Default::default()
+            }),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn detects_static_string_expressions() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "test.ts",
+            r#"
+__('first half ' + 'second half');
+__p(('menu' as const), 'File'!);
+__(`Hello ${'world'}` satisfies string);
+__(`Hello ${name}`);
+__(message);
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.ts:4
+msgid "Hello world"
+msgstr ""
+
+#: test.ts:2
+msgid "first half second half"
+msgstr ""
+
+#: test.ts:3
+msgctxt "menu"
+msgid "File"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn reports_calls_that_cant_be_extracted() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let diagnostics = parse(
+            "test.ts",
+            r#"
+__(someVariable);
+__n('1 file', `${count} files`, count);
+  __p('menu');
+__('Extracted');
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "test.ts:2:4: Skipping `__` call: argument is not a string literal",
+                "test.ts:3:18: Skipping `__n` call: template literal has interpolated expressions",
+                "test.ts:4:3: Skipping `__p` call: expected 2 arguments, found 1",
+            ]
+        );
+        assert!(pot
+            .lock()
+            .unwrap()
+            .to_string(None)
+            .unwrap()
+            .contains("msgid \"Extracted\""));
+    }
+
+    #[test]
+    fn resolves_constants_in_scope() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let diagnostics = parse(
+            "test.ts",
+            r#"
+import { label } from './labels';
+const DOMAIN = 'billing';
+const CTX = 'checkout' as const;
+const PREFIX = `Pay`;
+const MESSAGES = {
+    save: 'Save',
+    'cancel': 'Cancel ' + CTX,
+    nested: { title: PREFIX + ' now' },
+    CTX,
+};
+const LABELS = MESSAGES;
+
+__dp(DOMAIN, CTX, 'Pay');
+__(MESSAGES.save);
+__(MESSAGES['cancel']);
+__(LABELS.nested.title);
+__p(MESSAGES.CTX, 'Shorthand property');
+function later() {
+    return __(LATER);
+}
+const LATER = 'Declared after use';
+
+function shadowed(CTX) {
+    const MESSAGES = { save: 'Shadowed save' };
+    __p(CTX, 'Shadowed by a parameter');
+    __(MESSAGES.save);
+    {
+        const CTX = 'block';
+        __p(CTX, 'Shadowed in a block');
+    }
+}
+__(label);
+__(MESSAGES.missing);
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "test.ts:26:9: Skipping `__p` call: argument is not a string literal",
+                "test.ts:33:4: Skipping `__` call: argument is not a string literal",
+                "test.ts:34:4: Skipping `__` call: argument is not a string literal",
+            ]
+        );
+
+        let pot = pot.lock().unwrap();
+        assert_eq!(
+            pot.to_string(Some("billing")).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.ts:14
+msgctxt "checkout"
+msgid "Pay"
+msgstr ""
+"#
+        );
+        assert_eq!(
+            pot.to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.ts:16
+msgid "Cancel checkout"
+msgstr ""
+
+#: test.ts:20
+msgid "Declared after use"
+msgstr ""
+
+#: test.ts:17
+msgid "Pay now"
+msgstr ""
+
+#: test.ts:15
+msgid "Save"
+msgstr ""
+
+#: test.ts:27
+msgid "Shadowed save"
+msgstr ""
+
+#: test.ts:30
+msgctxt "block"
+msgid "Shadowed in a block"
+msgstr ""
+
+#: test.ts:18
+msgctxt "checkout"
+msgid "Shorthand property"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn reports_arguments_referring_to_mutable_bindings() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let diagnostics = parse(
+            "test.js",
+            r#"
+let CTX = 'menu';
+var TITLE = 'Title';
+const MESSAGES = { save: 'Save' };
+const ALIAS = CTX;
+MESSAGES.save = 'Overridden';
+const CYCLE = OTHER;
+const OTHER = CYCLE;
+
+__p(CTX, 'File');
+__(TITLE);
+__(MESSAGES.save);
+__(ALIAS);
+__(`${CTX} file`);
+__(CYCLE);
+if (true) {
+    var HOISTED = 'Hoisted';
+}
+__(HOISTED);
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "test.js:10:5: Skipping `__p` call: argument refers to a binding that isn't constant",
+                "test.js:11:4: Skipping `__` call: argument refers to a binding that isn't constant",
+                "test.js:12:4: Skipping `__` call: argument refers to a binding that isn't constant",
+                "test.js:13:4: Skipping `__` call: argument refers to a binding that isn't constant",
+                "test.js:14:7: Skipping `__` call: argument refers to a binding that isn't constant",
+                "test.js:15:4: Skipping `__` call: argument is not a string literal",
+                "test.js:19:4: Skipping `__` call: argument refers to a binding that isn't constant",
+            ]
+        );
+        assert!(pot.lock().unwrap().domains.is_empty());
+    }
+
+    #[test]
+    fn reports_calls_with_too_few_arguments_for_every_keyword() {
+        for spec in crate::keywords::DEFAULT_KEYWORDS {
+            let keyword: Keyword = spec.parse().unwrap();
+            let arity = keyword.arity();
+            for found in 0..arity {
+                let args = (0..found)
+                    .map(|index| format!("'arg {}'", index))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let source = format!("{}({});\n__('Still extracted');", keyword.name, args);
+
+                let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+                let diagnostics = parse("test.js", &source, Arc::clone(&pot));
+                assert_eq!(
+                    diagnostics
+                        .iter()
+                        .map(|diagnostic| diagnostic.to_string())
+                        .collect::<Vec<_>>(),
+                    vec![format!(
+                        "test.js:1:1: Skipping `{}` call: expected {} arguments, found {}",
+                        keyword.name, arity, found
+                    )],
+                    "{}",
+                    source
+                );
+
+                let pot = pot.lock().unwrap();
+                assert_eq!(pot.domains.len(), 1, "{}", source);
+                assert!(pot
+                    .to_string(None)
+                    .unwrap()
+                    .contains("msgid \"Still extracted\""));
+            }
+        }
+    }
+
+    #[test]
+    fn detects_custom_keywords() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse_with_options(
+            "test.js",
+            r#"
+t("Hello");
+i18n.translate("menu", "File");
+tdn("billing", "1 invoice", "%d invoices", count);
+__("Not a keyword anymore");
+"#,
+            Arc::clone(&pot),
+            &ExtractOptions {
+                keywords: Keywords::new(
+                    false,
+                    ["t", "translate:1c,2", "tdn:1d,2,3"].map(|spec| spec.parse().unwrap()),
+                ),
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.js:2
+msgid "Hello"
+msgstr ""
+
+#: test.js:3
+msgctxt "menu"
+msgid "File"
+msgstr ""
+"#
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(Some("billing")).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.js:4
+#, c-format
+msgid "1 invoice"
+msgid_plural "%d invoices"
+msgstr[0] ""
+msgstr[1] ""
+"#
+        );
+    }
+
+    #[test]
+    fn detects_format_flags() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "test.js",
+            r#"
+__('Hello {name}');
+__('{count, plural, one {# file} other {# files}}');
+__('Discount: 100%');
+/* xgettext:no-c-format */ __('Progress: 50%d');
+// xgettext:no-c-format -- not a placeholder
+const progress = __n('%d%s done', '%d%s done');
+__('Reported %s');
+// xgettext:python-brace-format
+const label = __('Total');
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.js:7
+#, no-c-format
+msgid "%d%s done"
+msgid_plural "%d%s done"
+msgstr[0] ""
+msgstr[1] ""
+
+#: test.js:4
+msgid "Discount: 100%"
+msgstr ""
+
+#: test.js:2
+#, python-brace-format
+msgid "Hello {name}"
+msgstr ""
+
+#: test.js:5
+#, no-c-format
+msgid "Progress: 50%d"
+msgstr ""
+
+#: test.js:8
+#, c-format
+msgid "Reported %s"
+msgstr ""
+
+#: test.js:10
+#, python-brace-format
+msgid "Total"
+msgstr ""
+
+#: test.js:3
+#, icu-format
+msgid "{count, plural, one {# file} other {# files}}"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn prefers_no_format_flags_across_calls() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        parse(
+            "test.js",
+            r#"
+__('Up %s');
+/* xgettext:no-c-format */ __('Up %s');
+__('Up %s');
+"#,
+            Arc::clone(&pot),
+        );
+        let pot = pot.lock().unwrap();
+        assert_eq!(
+            pot.domains["default"]
+                .get(&POTMessageID {
+                    msgctx: None,
+                    msgid: "Up %s".to_string(),
+                    msgid_plural: None,
+                })
+                .unwrap()
+                .flags,
+            BTreeSet::from(["no-c-format".to_string()])
+        );
+    }
+
+    #[test]
+    fn applies_i18n_comment_directives() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let diagnostics = parse(
+            "test.tsx",
+            r#"
+/* i18n: context=toolbar, max-length=20, no-wrap */ __('Save');
+// i18n: context="Save, then close"
+const label = __('Save');
+// i18n: max-length=30
+const again = /* i18n: max-length=short, colour=red */ __('Save');
+<p>
+  {/* i18n: no-c-format */}
+  {__('Up 5%d')}
+</p>;
+"#,
+            Arc::clone(&pot),
+        );
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "test.tsx:6:15: Ignoring comment on `__` call: `max-length=short` isn't a valid i18n directive",
+                "test.tsx:6:15: Ignoring comment on `__` call: `colour=red` isn't a valid i18n directive",
+            ]
+        );
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.tsx:6
+#, max-length:30
+msgid "Save"
+msgstr ""
+
+#: test.tsx:9
+#, no-c-format
+msgid "Up 5%d"
+msgstr ""
+
+#: test.tsx:4
+msgctxt "Save, then close"
+msgid "Save"
+msgstr ""
+
+#: test.tsx:2
+#, max-length:20, no-wrap
+msgctxt "toolbar"
+msgid "Save"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn honours_suppression_comments() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let (diagnostics, suppressed) = parse_with_suppressed(
+            "test.tsx",
+            r#"
+__('Extracted');
+// gettext-extractor-disable-next-line -- lodash-style helper, not gettext
+__(someVariable);
+__('Extracted on the line after');
+
+/* gettext-extractor-disable */
+__('Fixture string');
+const fixture = <Trans>Fixture component</Trans>;
+/* gettext-extractor-disable */
+__n('Still disabled', 'Still disabled');
+/* gettext-extractor-enable */
+
+__('Extracted after enable');
+/* gettext-extractor-disable */
+__('Disabled until the end of the file');
+"#,
+            Arc::clone(&pot),
+            &ExtractOptions {
+                jsx_components: vec!["Trans".to_string()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(diagnostics, vec![]);
+        assert_eq!(suppressed, 5);
+        assert_eq!(
+            pot.lock().unwrap().to_string(None).unwrap(),
+            r#"msgid ""
+msgstr ""
+"MIME-Version: 1.0\n"
+"Content-Type: text/plain; charset=utf-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#: test.tsx:2
+msgid "Extracted"
+msgstr ""
+
+#: test.tsx:14
+msgid "Extracted after enable"
+msgstr ""
+
+#: test.tsx:5
+msgid "Extracted on the line after"
+msgstr ""
+"#
+        );
+    }
+
+    #[test]
+    fn honours_file_suppression_comments() {
+        let pot = Arc::new(Mutex::new(crate::pot::POT::new(None)));
+        let (diagnostics, suppressed) = parse_with_suppressed(
+            "test.js",
+            r#"
+import { __ } from 'lodash-i18n-helpers';
+__('First');
+// gettext-extractor-disable-file
+__(notGettext);
+"#,
+            Arc::clone(&pot),
+            &ExtractOptions::default(),
+        );
+        assert_eq!(diagnostics, vec![]);
+        assert_eq!(suppressed, 2);
+        assert!(pot.lock().unwrap().domains.is_empty());
+    }
+
+    use swc_ecma_visit::VisitWith;
+
+    fn parse(filename: &str, source: &str, pot: Arc<Mutex<crate::pot::POT>>) -> Vec<Diagnostic> {
+        parse_with_options(filename, source, pot, &ExtractOptions::default())
+    }
+
+    fn parse_with_options(
+        filename: &str,
+        source: &str,
+        pot: Arc<Mutex<crate::pot::POT>>,
+        options: &ExtractOptions,
+    ) -> Vec<Diagnostic> {
+        parse_with_suppressed(filename, source, pot, options).0
+    }
+
+    /// Also returns how many calls were suppressed by comments
+    fn parse_with_suppressed(
+        filename: &str,
+        source: &str,
+        pot: Arc<Mutex<crate::pot::POT>>,
+        options: &ExtractOptions,
+    ) -> (Vec<Diagnostic>, usize) {
+        let cm: Lrc<SourceMap> = Default::default();
+        let comments: swc_common::comments::SingleThreadedComments = Default::default();
+        let references_relative_to = PathBuf::from(".");
+        let mut visitor = GettextVisitor::new(
+            pot,
+            Lrc::clone(&cm),
+            Some(&comments),
+            &references_relative_to,
+            options,
+        );
         let fm = cm.new_source_file(FileName::Custom(filename.into()), source.into());
         let lexer = Lexer::new(
             swc_ecma_parser::Syntax::Typescript(swc_ecma_parser::TsConfig {
@@ -547,5 +2385,6 @@ class MyController {}
         let mut parser = Parser::new_from(lexer);
         let module = parser.parse_module().unwrap();
         module.visit_with(&mut visitor);
+        (visitor.diagnostics, visitor.suppressed)
     }
 }
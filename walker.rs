@@ -5,7 +5,9 @@ pub fn find_ts_files(
     path: PathBuf,
     exclude: Vec<String>,
 ) -> Result<impl Iterator<Item = walkdir::DirEntry>, walkdir::Error> {
+    // Sorted, so that the order of messages found doesn't depend on the file system
     Ok(WalkDir::new(path)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|entry| entry.ok())
         .filter(|entry| {
@@ -25,7 +27,7 @@ pub fn find_ts_files(
                         false
                     } else {
                         // Filter out all files with extensions other than `ts` or `tsx` or `js` or `jsx`
-                        entry.path().extension().map_or(false, |ext| {
+                        entry.path().extension().is_some_and(|ext| {
                             ext == "ts" || ext == "tsx" || ext == "js" || ext == "jsx"
                         })
                     }
@@ -39,14 +41,22 @@ use std::sync::{Arc, Mutex};
 
 use swc_common::sync::Lrc;
 use swc_common::{
+    comments::{Comments, SingleThreadedComments},
     errors::{ColorConfig, Handler},
     SourceMap,
 };
+use swc_ecma_ast::Module;
 use swc_ecma_parser::{lexer::Lexer, EsConfig, Parser, StringInput, Syntax, TsConfig};
 use swc_ecma_visit::VisitWith;
 
-/// Extract gettext strings from a source file
-pub fn parse_file(path: &Path, pot: Arc<Mutex<crate::pot::POT>>, references_relative_to: &PathBuf) {
+/// Parses a source file, emitting syntax errors to the handler.
+/// Returns `None` when the file can't be read or parsed at all.
+pub fn load_module(
+    path: &Path,
+    cm: &Lrc<SourceMap>,
+    handler: &Handler,
+    comments: Option<&dyn Comments>,
+) -> Option<Module> {
     let syntax = match path.extension() {
         Some(os_str) => match os_str.to_str() {
             Some("d.ts") => Syntax::Typescript(TsConfig {
@@ -77,38 +87,101 @@ pub fn parse_file(path: &Path, pot: Arc<Mutex<crate::pot::POT>>, references_rela
         _ => panic!("Unknown extension"),
     };
 
-    let cm: Lrc<SourceMap> = Default::default();
-    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
-    let comments: swc_common::comments::SingleThreadedComments = Default::default();
-
-    let fm = cm.load_file(path).expect("Failed to load file");
+    let fm = match cm.load_file(path) {
+        Ok(fm) => fm,
+        Err(error) => {
+            handler.err(&format!("Failed to read {}: {}", path.display(), error));
+            return None;
+        }
+    };
     let lexer = Lexer::new(
         syntax,
         // EsVersion defaults to es5
         swc_ecma_ast::EsVersion::EsNext,
         StringInput::from(&*fm),
-        Some(&comments),
+        comments,
     );
     let mut parser = Parser::new_from(lexer);
 
     for e in parser.take_errors() {
-        e.into_diagnostic(&handler).emit();
+        e.into_diagnostic(handler).emit();
     }
 
-    let module = parser
+    parser
         .parse_module()
         .map_err(|e| {
             // Unrecoverable fatal error occurred
-            e.into_diagnostic(&handler).emit()
+            e.into_diagnostic(handler).emit()
         })
-        .expect("failed to parser module");
+        .ok()
+}
+
+/// Extract gettext strings from a source file
+pub fn parse_file(
+    path: &Path,
+    file_order: usize,
+    pot: Arc<Mutex<crate::pot::POT>>,
+    references_relative_to: &PathBuf,
+    options: &crate::visitor::ExtractOptions,
+    diagnostics: Arc<Mutex<crate::diagnostics::Diagnostics>>,
+    warnings_as_errors: bool,
+) {
+    let cm: Lrc<SourceMap> = Default::default();
+    let handler = Handler::with_tty_emitter(ColorConfig::Auto, true, false, Some(cm.clone()));
+    let comments: SingleThreadedComments = Default::default();
+
+    // The handler has already reported why the file couldn't be read or parsed
+    let Some(module) = load_module(path, &cm, &handler, Some(&comments)) else {
+        diagnostics.lock().unwrap().add_unparseable_file();
+        return;
+    };
 
-    let mut visitor = crate::visitor::GettextVisitor {
+    let mut visitor = crate::visitor::GettextVisitor::new(
         pot,
-        cm: Lrc::clone(&cm),
-        comments: Some(&comments),
+        Lrc::clone(&cm),
+        Some(&comments),
         references_relative_to,
-    };
+        options,
+    );
+    visitor.file_order = file_order;
 
     module.visit_with(&mut visitor);
+
+    for diagnostic in &visitor.diagnostics {
+        diagnostic.emit(&handler, warnings_as_errors);
+    }
+    let mut diagnostics = diagnostics.lock().unwrap();
+    diagnostics.extend(visitor.diagnostics);
+    diagnostics.add_suppressed(visitor.suppressed);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// Collects what the handler emits
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reports_files_that_cannot_be_read() {
+        let cm: Lrc<SourceMap> = Default::default();
+        let output = Output::default();
+        let handler = Handler::with_emitter_writer(Box::new(output.clone()), Some(cm.clone()));
+        let path = Path::new("./tests/unparseable/latin1.ts");
+        assert!(load_module(path, &cm, &handler, None).is_none());
+        let output = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("Failed to read ./tests/unparseable/latin1.ts"));
+    }
 }